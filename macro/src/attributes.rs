@@ -1,5 +1,5 @@
 use proc_macro2::{TokenStream, Ident, Literal/*, Span*/};
-use quote::{quote, ToTokens};
+use quote::{quote, quote_spanned, ToTokens};
 use syn::{
     Block,
     Token,
@@ -8,7 +8,8 @@ use syn::{
     parse::{Parse, ParseStream},
     ext::IdentExt
 };
-use rand::Rng;
+use proc_macro_error::abort;
+use crate::refs::next_ref_value;
 //use std::sync::Arc;
 
 
@@ -44,32 +45,60 @@ impl<'a> Attributes<'a>{
     pub fn empty()->Self{
         Self{list:vec![]}
     }
+    pub fn is_empty(&self)->bool{
+        self.list.is_empty()
+    }
+    /// The `@name` attribute, if any
+    pub fn get_ref(&self)->Option<&Attribute<'a>>{
+        self.list.iter().find(|attr| matches!(attr.attr_type, AttributeType::Ref))
+    }
     pub fn to_properties(&self/*, names:Arc<Vec<String>>*/)->Vec<TokenStream>{
         let mut properties = vec![];
+        let mut listeners = vec![];
         //let mut used = vec![];
+        // every call is spanned by its attribute, so errors point at it
+        let mut listeners_span = None;
         for attr in &self.list{
             let name = &attr.name;
+            let span = attr.name.first().unwrap().span();
+            if let AttributeType::Ref = attr.attr_type{
+                let name = attr.get_name();
+                let value = attr.get_value();
+                properties.push(quote_spanned!(span=>
+                    .__reff(Some((#name, #value)))
+                ));
+                continue;
+            }
+            if let AttributeType::Event = attr.attr_type{
+                listeners.push(attr.get_listener());
+                listeners_span.get_or_insert(span);
+                continue;
+            }
+            if let AttributeType::Bind = attr.attr_type{
+                abort!(attr.name, "`bind:` is only supported on html elements");
+            }
+            if attr.is_key(){
+                let value = attr.get_value();
+                properties.push(quote_spanned!(span=>
+                    .__key(Some(::std::string::ToString::to_string(&#value)))
+                ));
+                continue;
+            }
             let value = match attr.attr_type{
                 AttributeType::String=>{
-                    if attr.value.is_some(){
-                        let value = attr.get_value();
-                        quote!(:&#value)
-                    }else{
-                        quote!(:&#name)
-                    }
-                }
-                _=>{
-                    if attr.value.is_some(){
-                        let value = attr.get_value();
-                        quote!(:#value)
-                    }else{
-                        quote!()
-                    }
+                    let value = attr.get_value();
+                    quote!(&#value)
                 }
+                _=>attr.get_value()
             };
             //used.push(name.to_string());
-            properties.push(quote!(
-                #name #value
+            properties.push(quote_spanned!(span=>
+                .#name(#value)
+            ));
+        }
+        if let Some(span) = listeners_span.filter(|_| cfg!(feature = "dom")){
+            properties.push(quote_spanned!(span=>
+                .__listeners(vec![#(#listeners),*])
             ));
         }
         /*
@@ -87,26 +116,43 @@ impl<'a> Attributes<'a>{
     }
     pub fn to_token_stream(&self)->TokenStream{
         let mut attrs = vec![];
+        let mut listeners = vec![];
+        let mut bindings = vec![];
         let mut ref_field = quote!(reff:None);
+        let mut key_field = quote!(key:None);
         for attr in &self.list{
             let name = attr.get_name();
             let value = attr.get_value();
+            if attr.is_key(){
+                key_field = quote!{key: Some(::std::string::ToString::to_string(&#value))};
+                continue;
+            }
             let mut append = true;
             let value = match attr.attr_type{
                 AttributeType::Bool=>{
                     quote!{flow_html::AttributeValue::Bool(#value)}
                 }
                 AttributeType::Str=>{
-                    quote!{flow_html::AttributeValue::Str(#value)}
+                    quote!{flow_html::IntoAttribute::into_attribute_value(#value)}
                 }
                 AttributeType::String=>{
-                    quote!{flow_html::AttributeValue::Str(&#value)}
+                    quote!{flow_html::IntoAttribute::into_attribute_value(&#value)}
                 }
                 AttributeType::Ref=>{
                     ref_field = quote!{reff: Some((#name, #value))};
                     append = false;
                     quote!()
                 }
+                AttributeType::Event=>{
+                    listeners.push(attr.get_listener());
+                    append = false;
+                    quote!()
+                }
+                AttributeType::Bind=>{
+                    bindings.push(attr.get_binding());
+                    append = false;
+                    quote!()
+                }
             };
             if append{
                 attrs.push(quote!(
@@ -114,13 +160,21 @@ impl<'a> Attributes<'a>{
                 ));
             }
         }
+        let listeners_field = if cfg!(feature = "dom"){
+            quote!(, listeners: vec![#(#listeners),*])
+        }else{
+            quote!()
+        };
         quote!{
             #ref_field,
+            #key_field,
+            bindings: vec![#(#bindings),*],
             attributes:{
                 let mut map = std::collections::BTreeMap::new();
                 #(#attrs)*
                 map
             }
+            #listeners_field
         }.into()
     }
 }
@@ -135,7 +189,9 @@ pub enum AttributeType{
     Bool,
     Str,
     String,
-    Ref
+    Ref,
+    Event,
+    Bind
 }
 pub struct Attribute<'a>{
     pub name: AttributeName,
@@ -154,6 +210,27 @@ impl<'a> Attribute<'a>{
         items.fold(first, |a, b|format!("{}-{}", a, b))
     }
 
+    /// `key={...}` identifies an item of a list, it is not rendered as an attribute,
+    /// a bare `key` is short for `key={key}`
+    pub fn is_key(&self)->bool{
+        matches!(self.attr_type, AttributeType::Str) && self.get_name() == "key"
+    }
+
+    /// `on:event={handler}` as a `flow_html::dom::Listener`
+    pub fn get_listener(&self)->TokenStream{
+        let name = self.get_name();
+        let value = self.get_value();
+        quote!(flow_html::dom::Listener::new(#name, #value))
+    }
+
+    /// `bind:value={state}` as a `flow_html::FormBinding`, the state is cloned
+    /// so it can be given by value or by reference
+    pub fn get_binding(&self)->TokenStream{
+        let value = self.get_value();
+        let constructor = Ident::new(&self.get_name(), self.name.first().unwrap().span());
+        quote!(flow_html::FormBinding::#constructor((#value).clone()))
+    }
+
     pub fn get_value(&self)->TokenStream{
         match &self.value {
             Some(value)=>{
@@ -171,17 +248,7 @@ impl<'a> Attribute<'a>{
                 
             }
             None => {
-                match self.attr_type{
-                    AttributeType::Ref=>{
-                        let mut rng = rand::thread_rng();
-                        let code = format!("ref_{}", rng.gen::<u32>());
-                        quote!(#code)
-                    }
-                    _=>{
-                        self.name.to_token_stream()
-                    }
-                }
-                
+                self.name.to_token_stream()
             }
         }
     }
@@ -199,9 +266,23 @@ impl<'a> Parse for Attribute<'a>{
         }else if input.peek(Token![@]){
             input.parse::<Token![@]>()?;
             attr_type = AttributeType::Ref;
+        }else if input.peek(syn::Ident::peek_any) && input.peek2(Token![:]){
+            let prefix = input.call(syn::Ident::parse_any)?;
+            attr_type = match prefix.to_string().as_str(){
+                "on"=>AttributeType::Event,
+                "bind"=>AttributeType::Bind,
+                _=>abort!(prefix.span(), format!("Unknown attribute prefix '{}:'", prefix))
+            };
+            input.parse::<Token![:]>()?;
         }
         
         let name = AttributeName::parse_separated_nonempty_with(input, syn::Ident::parse_any)?;
+        if let AttributeType::Bind = attr_type{
+            let property = name.to_string();
+            if property != "value" && property != "checked"{
+                abort!(name, format!("Unsupported binding 'bind:{}', expected 'bind:value' or 'bind:checked'", property));
+            }
+        }
         if input.peek(Token![=]){
             input.parse::<Token![=]>()?;
             let value;
@@ -214,6 +295,15 @@ impl<'a> Parse for Attribute<'a>{
             }
             return Ok(Attribute::new(name, attr_type, Some(value)));
         }
+        match attr_type{
+            AttributeType::Ref=>{
+                let value = Literal::string(&next_ref_value());
+                return Ok(Attribute::new(name, attr_type, Some(AttributeValue::Literal(value))));
+            }
+            AttributeType::Event=>abort!(name, format!("Event handler is missing for 'on:{}'", name.to_string())),
+            AttributeType::Bind=>abort!(name, format!("Bound state is missing for 'bind:{}'", name.to_string())),
+            _=>{}
+        }
         Ok(Attribute::new(name, attr_type, None))
     }
 }
@@ -0,0 +1,265 @@
+use proc_macro2::{TokenStream, Ident};
+use quote::{quote, quote_spanned, format_ident};
+use syn::{
+    Attribute, Generics, GenericParam, Type, Visibility, Expr, Token, parse_quote, parse_quote_spanned,
+    parse::ParseStream
+};
+use proc_macro_error::abort;
+
+/// How a field of the props struct gets its value
+pub enum PropKind{
+    /// Must be given unless its type implements `flow_html::OptionalProp`,
+    /// `build()` does not compile otherwise
+    Required,
+    /// `Default::default()` or the `#[prop(default = ...)]` expression
+    Default(Option<TokenStream>),
+    /// `#[slot]` field, given a value it changes the slot type
+    Slot(Ident)
+}
+
+pub struct Prop{
+    pub ident:Ident,
+    pub ty:Type,
+    pub kind:PropKind
+}
+
+impl Prop{
+    pub fn new(ident:Ident, ty:Type, kind:PropKind)->Self{
+        Self{ident, ty, kind}
+    }
+}
+
+/// `#[prop(default)]` fields get a default, the others are required unless
+/// their type turns out to be a `flow_html::OptionalProp`
+pub fn prop_kind(attrs:&[Attribute])->PropKind{
+    let attr = match attrs.iter().find(|attr| is_prop_attribute(attr)){
+        Some(attr)=>attr,
+        None=>return PropKind::Required
+    };
+    let parsed = attr.parse_args_with(|input:ParseStream|{
+        let key:Ident = input.parse()?;
+        if key != "default"{
+            abort!(key, format!("Unknown prop option '{}', expected 'default'", key));
+        }
+        if input.peek(Token![=]){
+            input.parse::<Token![=]>()?;
+            let value = input.parse::<Expr>()?;
+            return Ok(Some(quote!(#value)));
+        }
+        Ok(None)
+    });
+    match parsed{
+        Ok(value)=>PropKind::Default(value),
+        Err(err)=>abort!(err.span(), err.to_string())
+    }
+}
+
+/// Fields taking `@ref`, `key={...}` and `on:event` of a component, under
+/// reserved names so they cannot clash with its own props
+pub fn hidden_props()->Vec<Prop>{
+    let mut props = vec![
+        Prop::new(format_ident!("__reff"), parse_quote!(Option<(&'static str, &'static str)>), PropKind::Default(None)),
+        Prop::new(format_ident!("__key"), parse_quote!(Option<String>), PropKind::Default(None))
+    ];
+    if cfg!(feature = "dom"){
+        props.push(Prop::new(
+            format_ident!("__listeners"),
+            parse_quote!(Vec<flow_html::dom::Listener<'static>>),
+            PropKind::Default(None)
+        ));
+    }
+    props
+}
+
+pub fn is_prop_attribute(attr:&Attribute)->bool{
+    attr.path.is_ident("prop")
+}
+
+fn is_slot_param(ident:&Ident, props:&[Prop])->bool{
+    props.iter().any(|prop| matches!(&prop.kind, PropKind::Slot(param) if param == ident))
+}
+
+/// Generic arguments of `generics`, each type parameter passed to `replace`
+fn type_args(generics:&Generics, replace:impl Fn(&Ident)->Option<TokenStream>)->Vec<TokenStream>{
+    generics.params.iter().map(|param|{
+        match param{
+            GenericParam::Lifetime(def)=>{
+                let lifetime = &def.lifetime;
+                quote!(#lifetime)
+            }
+            GenericParam::Type(param)=>{
+                let ident = &param.ident;
+                replace(ident).unwrap_or_else(|| quote!(#ident))
+            }
+            GenericParam::Const(param)=>{
+                let ident = &param.ident;
+                quote!(#ident)
+            }
+        }
+    }).collect()
+}
+
+/// `Name::builder()` returning a `NameBuilder` with a setter per field.
+/// Required fields are tracked by `PropSet`/`PropUnset` type parameters so
+/// `build()` only exists once all of them are given, unfilled slots are `()`.
+pub fn builder(vis:&Visibility, name:&Ident, generics:&Generics, props:&[Prop])->TokenStream{
+    let builder_name = format_ident!("{}Builder", name);
+    let required:Vec<&Prop> = props.iter()
+        .filter(|prop| matches!(prop.kind, PropKind::Required))
+        .collect();
+    let markers:Vec<Ident> = (0..required.len()).map(|index| format_ident!("__P{}", index)).collect();
+
+    let mut builder_generics = generics.clone();
+    for marker in &markers{
+        builder_generics.params.push(parse_quote!(#marker));
+    }
+    let (impl_generics, type_generics, where_clause) = builder_generics.split_for_impl();
+    let (_, struct_generics, _) = generics.split_for_impl();
+    let args = type_args(generics, |_| None);
+    let builder_type = |args:&[TokenStream], markers:&[TokenStream]|{
+        quote!(#builder_name<#(#args,)* #(#markers),*>)
+    };
+    let marker_args:Vec<TokenStream> = markers.iter().map(|marker| quote!(#marker)).collect();
+    let idents:Vec<&Ident> = props.iter().map(|prop| &prop.ident).collect();
+
+    let mut fields = vec![];
+    let mut setters = vec![];
+    let mut required_ts = vec![];
+    // bounds of `build()` itself rather than of its impl, so a missing prop
+    // reports the `on_unimplemented` message of `OptionalProp`
+    let mut build_predicates:Vec<syn::WherePredicate> = vec![];
+    let mut build_fields = vec![];
+    let mut required_index = 0;
+    for prop in props{
+        let ident = &prop.ident;
+        let ty = &prop.ty;
+        let others:Vec<&&Ident> = idents.iter().filter(|other| **other != ident).collect();
+        match &prop.kind{
+            PropKind::Required=>{
+                fields.push(quote!(#ident: Option<#ty>));
+                let mut markers = marker_args.clone();
+                markers[required_index] = quote!(flow_html::PropSet);
+                let returns = builder_type(&args, &markers);
+                setters.push(quote!(
+                    impl #impl_generics #builder_name #type_generics #where_clause {
+                        pub fn #ident(self, #ident:#ty)->#returns{
+                            #builder_name{
+                                #(#others: self.#others,)*
+                                #ident: Some(#ident),
+                                __props: ::core::marker::PhantomData
+                            }
+                        }
+                    }
+                ));
+                let marker = &marker_args[required_index];
+                let required_trait = format_ident!("__{}_{}", name, ident);
+                // an unset prop resolves through `OptionalProp` of its type,
+                // otherwise `build()` reports the missing impl, the notes point
+                // at the field through the span
+                required_ts.push(quote_spanned!(ident.span()=>
+                    #[doc(hidden)]
+                    #[allow(non_camel_case_types)]
+                    #vis trait #required_trait<__T> {
+                        fn resolve(value:Option<__T>)->__T;
+                    }
+                    impl<__T> #required_trait<__T> for flow_html::PropSet {
+                        fn resolve(value:Option<__T>)->__T{
+                            value.unwrap()
+                        }
+                    }
+                    impl<__T:flow_html::OptionalProp> #required_trait<__T> for flow_html::PropUnset {
+                        fn resolve(_value:Option<__T>)->__T{
+                            __T::unset()
+                        }
+                    }
+                ));
+                build_predicates.push(parse_quote_spanned!(ident.span()=> #marker: #required_trait<#ty>));
+                build_fields.push(quote!(#ident: <#marker as #required_trait<#ty>>::resolve(self.#ident)));
+                required_index += 1;
+            }
+            PropKind::Default(value)=>{
+                fields.push(quote!(#ident: Option<#ty>));
+                setters.push(quote!(
+                    impl #impl_generics #builder_name #type_generics #where_clause {
+                        pub fn #ident(mut self, #ident:#ty)->Self{
+                            self.#ident = Some(#ident);
+                            self
+                        }
+                    }
+                ));
+                build_fields.push(match value{
+                    Some(value)=>quote!(#ident: self.#ident.unwrap_or_else(|| #value)),
+                    None=>{
+                        build_predicates.push(parse_quote!(#ty: Default));
+                        quote!(#ident: self.#ident.unwrap_or_default())
+                    }
+                });
+            }
+            PropKind::Slot(param)=>{
+                fields.push(quote!(#ident: #ty));
+                let bounds = generics.type_params()
+                    .find(|type_param| &type_param.ident == param)
+                    .map(|type_param| type_param.bounds.clone())
+                    .unwrap_or_default();
+                let slot_args = type_args(generics, |ident| (ident == param).then(|| quote!(__S)));
+                let returns = builder_type(&slot_args, &marker_args);
+                setters.push(quote!(
+                    impl #impl_generics #builder_name #type_generics #where_clause {
+                        pub fn #ident<__S: #bounds>(self, #ident:__S)->#returns{
+                            #builder_name{
+                                #(#others: self.#others,)*
+                                #ident: Some(#ident),
+                                __props: ::core::marker::PhantomData
+                            }
+                        }
+                    }
+                ));
+                build_fields.push(quote!(#ident: self.#ident));
+            }
+        }
+    }
+
+    // builder() fixes empty slots to `()`
+    let mut init_generics = generics.clone();
+    init_generics.params = init_generics.params.into_iter().filter(|param|{
+        !matches!(param, GenericParam::Type(param) if is_slot_param(&param.ident, props))
+    }).collect();
+    let has_slots = props.iter().any(|prop| matches!(prop.kind, PropKind::Slot(_)));
+    if has_slots{
+        init_generics.where_clause = None;
+    }
+    let (init_impl, _, init_where) = init_generics.split_for_impl();
+    let init_args = type_args(generics, |ident| is_slot_param(ident, props).then(|| quote!(())));
+    let unset:Vec<TokenStream> = markers.iter().map(|_| quote!(flow_html::PropUnset)).collect();
+    let init_type = builder_type(&init_args, &unset);
+    let doc = format!("Builder of [`{}`], `tree!` uses it for `<{}>` elements", name, name);
+
+    quote!(
+        #[doc = #doc]
+        #vis struct #builder_name #impl_generics #where_clause {
+            #(#fields,)*
+            __props: ::core::marker::PhantomData<(#(#markers,)*)>
+        }
+
+        impl #init_impl #name<#(#init_args),*> #init_where {
+            pub fn builder()->#init_type{
+                #builder_name{
+                    #(#idents: None,)*
+                    __props: ::core::marker::PhantomData
+                }
+            }
+        }
+
+        #(#setters)*
+
+        #(#required_ts)*
+
+        impl #impl_generics #builder_name #type_generics #where_clause {
+            pub fn build(self)->#name #struct_generics where #(#build_predicates),* {
+                #name{
+                    #(#build_fields),*
+                }
+            }
+        }
+    )
+}
@@ -0,0 +1,234 @@
+use proc_macro2::{TokenStream, Span, Ident};
+use quote::{quote, quote_spanned, format_ident};
+use syn::spanned::Spanned;
+use syn::{
+    ItemFn, FnArg, Pat, Type, TypeParamBound, Lifetime, LifetimeDef,
+    GenericParam, ReturnType, Token, parse_quote,
+    punctuated::Punctuated,
+    visit_mut::{self, VisitMut}
+};
+use proc_macro_error::abort;
+use crate::slots;
+use crate::builder::{self, Prop, PropKind};
+
+/// Makes argument types usable as struct fields: elided lifetimes become
+/// `'__a` and every `impl Trait` becomes a generic parameter
+struct Generalize{
+    lifetime:Lifetime,
+    elided:bool,
+    params:Vec<(Ident, Punctuated<TypeParamBound, Token![+]>)>
+}
+
+impl VisitMut for Generalize{
+    fn visit_type_reference_mut(&mut self, ty:&mut syn::TypeReference){
+        if ty.lifetime.is_none(){
+            ty.lifetime = Some(self.lifetime.clone());
+            self.elided = true;
+        }
+        visit_mut::visit_type_reference_mut(self, ty);
+    }
+    fn visit_lifetime_mut(&mut self, lifetime:&mut Lifetime){
+        if lifetime.ident == "_"{
+            *lifetime = self.lifetime.clone();
+            self.elided = true;
+        }
+    }
+    fn visit_type_mut(&mut self, ty:&mut Type){
+        if let Type::ImplTrait(impl_trait) = ty{
+            let mut bounds = impl_trait.bounds.clone();
+            for bound in bounds.iter_mut(){
+                self.visit_type_param_bound_mut(bound);
+            }
+            let ident = format_ident!("__T{}", self.params.len());
+            self.params.push((ident.clone(), bounds));
+            *ty = parse_quote!(#ident);
+            return;
+        }
+        visit_mut::visit_type_mut(self, ty);
+    }
+}
+
+/// `impl Render` arguments are given a borrowed value when rendering to a string
+fn is_impl_render(ty:&Type)->bool{
+    match ty{
+        Type::ImplTrait(impl_trait)=>impl_trait.bounds.iter().any(|bound|{
+            match bound{
+                TypeParamBound::Trait(bound)=>{
+                    bound.path.segments.last().is_some_and(|segment| segment.ident == "Render")
+                }
+                _=>false
+            }
+        }),
+        _=>false
+    }
+}
+
+struct Field{
+    ident:Ident,
+    ty:Type,
+    borrowed:bool,
+    slot:bool,
+    kind:PropKind
+}
+
+/// Props struct named like the function, so `<Card title={..}>..</Card>`
+/// builds it in `tree!`, rendering it calls the function
+pub fn component(mut func:ItemFn)->TokenStream{
+    let name = func.sig.ident.clone();
+    let vis = func.vis.clone();
+    let lifetime = Lifetime::new("'__a", Span::call_site());
+    let mut generalize = Generalize{
+        lifetime:lifetime.clone(),
+        elided:false,
+        params:vec![]
+    };
+
+    let mut fields = vec![];
+    for input in func.sig.inputs.iter_mut(){
+        let arg = match input{
+            FnArg::Typed(arg)=>arg,
+            FnArg::Receiver(receiver)=>abort!(receiver, "A component cannot take `self`")
+        };
+        let ident = match &*arg.pat{
+            Pat::Ident(pat)=>pat.ident.clone(),
+            pat=>abort!(pat, "Component arguments must be plain identifiers")
+        };
+        let borrowed = is_impl_render(&arg.ty);
+        if ident == "children" && !borrowed{
+            abort!(arg.ty, "Component children must be declared as `children: impl Render`");
+        }
+        let slot = slots::is_slot(&arg.attrs);
+        arg.attrs.retain(|attr| !attr.path.is_ident("slot"));
+        if slot && (ident == "children" || !borrowed){
+            abort!(arg.ty, "Slots must be declared as `#[slot] name: impl Render`");
+        }
+        let kind = if ident == "children"{
+            PropKind::Default(None)
+        }else{
+            builder::prop_kind(&arg.attrs)
+        };
+        arg.attrs.retain(|attr| !builder::is_prop_attribute(attr));
+        generalize.visit_type_mut(&mut arg.ty);
+        let kind = if slot{
+            let ty = &arg.ty;
+            PropKind::Slot(parse_quote!(#ty))
+        }else{
+            kind
+        };
+        fields.push(Field{
+            ident,
+            ty:(*arg.ty).clone(),
+            borrowed,
+            slot,
+            kind
+        });
+    }
+
+    let generics = &mut func.sig.generics;
+    for (ident, bounds) in &generalize.params{
+        generics.params.push(parse_quote!(#ident: #bounds));
+    }
+    if generalize.elided{
+        // the returned markup may borrow from any argument
+        for param in generics.params.iter_mut(){
+            if let GenericParam::Type(param) = param{
+                param.bounds.push(TypeParamBound::Lifetime(lifetime.clone()));
+            }
+        }
+        generics.params.insert(0, GenericParam::Lifetime(LifetimeDef::new(lifetime.clone())));
+        if let ReturnType::Type(_, ty) = &mut func.sig.output{
+            if let Type::ImplTrait(impl_trait) = &mut **ty{
+                impl_trait.bounds.push(TypeParamBound::Lifetime(lifetime.clone()));
+            }
+        }
+    }
+
+    let (impl_generics, type_generics, where_clause) = func.sig.generics.split_for_impl();
+
+    let mut struct_fields = vec![];
+    let mut props = vec![];
+    let mut render_args = vec![];
+    let mut node_args = vec![];
+    for Field{ident, ty, borrowed, slot, kind} in fields{
+        // children and unfilled slots are `None`
+        let field_ty:Type = if slot || ident == "children"{
+            parse_quote!(Option<#ty>)
+        }else{
+            ty
+        };
+        struct_fields.push(quote!(pub #ident: #field_ty));
+        // a missing `Clone` impl is reported at the argument type
+        render_args.push(if borrowed{
+            quote!(flow_html::Borrowed(&self.#ident))
+        }else{
+            quote_spanned!(field_ty.span()=> <#field_ty as ::core::clone::Clone>::clone(&self.#ident))
+        });
+        node_args.push(quote!(self.#ident));
+        props.push(Prop::new(ident, field_ty, kind));
+    }
+    if !props.iter().any(|prop| prop.ident == "children"){
+        struct_fields.push(quote!(#[doc(hidden)] pub children: Option<()>));
+        props.push(Prop::new(format_ident!("children"), parse_quote!(Option<()>), PropKind::Default(None)));
+    }
+    for prop in builder::hidden_props(){
+        let (ident, ty) = (&prop.ident, &prop.ty);
+        struct_fields.push(quote!(#[doc(hidden)] pub #ident: #ty));
+        props.push(prop);
+    }
+    let builder_ts = builder::builder(&vis, &name, &func.sig.generics, &props);
+
+    let render_node_ts = if cfg!(feature = "dom"){
+        quote!(
+            fn render_node<__B:flow_html::dom::DomBackend>(
+                self,
+                parent:&mut __B::Element,
+                ctx:&mut flow_html::dom::Context<__B>
+            )->flow_html::ElementResult<()>{
+                let (reff, key, listeners) = (self.__reff, self.__key, self.__listeners);
+                if reff.is_none() && key.is_none() && listeners.is_empty(){
+                    return flow_html::Render::render_node(#name(#(#node_args),*), parent, ctx);
+                }
+                // `@ref`, `key` and `on:` go to the first element it renders
+                let index = ctx.backend.child_nodes(parent).len();
+                flow_html::Render::render_node(#name(#(#node_args),*), parent, ctx)?;
+                let el = ctx.backend.child_nodes(parent).into_iter()
+                    .skip(index)
+                    .find_map(|node| ctx.backend.node_element(&node));
+                if let Some(el) = el{
+                    if let Some((name, value)) = reff{
+                        ctx.set_ref(&el, name, value)?;
+                    }
+                    if let Some(key) = &key{
+                        ctx.backend.set_key(&el, key)?;
+                    }
+                    for listener in listeners{
+                        ctx.add_listener(&el, listener);
+                    }
+                }
+                Ok(())
+            }
+        )
+    }else{
+        quote!()
+    };
+    let doc = format!("Props of the [`{}`] component", name);
+
+    quote!(
+        #[allow(non_snake_case)]
+        #func
+
+        #[doc = #doc]
+        #vis struct #name #impl_generics #where_clause {
+            #(#struct_fields),*
+        }
+
+        #builder_ts
+
+        impl #impl_generics flow_html::Render for #name #type_generics #where_clause {
+            fn render<__W:core::fmt::Write>(&self, w:&mut __W)->core::fmt::Result{
+                flow_html::Render::render(&#name(#(#render_args),*), w)
+            }
+            #render_node_ts
+        }
+    )
+}
@@ -0,0 +1,227 @@
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::{Expr, Pat, Token, Result, braced};
+use syn::parse::{Parse, ParseStream};
+use crate::element::{Nodes, Element};
+use crate::refs::RefInfo;
+
+/// Markup of a branch: `{ nodes }` or a single `<element>`
+pub struct Branch<'a>{
+    nodes:Nodes<'a>
+}
+
+impl<'a> Parse for Branch<'a>{
+    fn parse(input: ParseStream) -> Result<Self> {
+        let nodes = if input.peek(Token![<]){
+            Nodes::from_element(input.parse::<Element>()?)
+        }else{
+            let content;
+            braced!(content in input);
+            content.parse::<Nodes>()?
+        };
+        Ok(Self{nodes})
+    }
+}
+
+impl<'a> Branch<'a>{
+    /// Elements of a branch are only rendered conditionally
+    fn collect_refs(&self, refs:&mut Vec<RefInfo>){
+        self.nodes.collect_refs(true, refs);
+    }
+}
+
+impl<'a> ToTokens for Branch<'a>{
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        self.nodes.get_tuples().to_tokens(tokens);
+    }
+}
+
+/// Wraps the branch at `index` out of `count` into nested `flow_html::Either`
+/// values, so that every branch evaluates to the same type
+fn either(index:usize, count:usize, value:TokenStream)->TokenStream{
+    let mut ts = if index + 1 == count{
+        value
+    }else{
+        quote!(flow_html::Either::Left(#value))
+    };
+    for _ in 0..index{
+        ts = quote!(flow_html::Either::Right(#ts));
+    }
+    ts
+}
+
+/// `if cond { ... } else if cond { ... } else { ... }`
+pub struct IfNode<'a>{
+    branches:Vec<(Expr, Branch<'a>)>,
+    otherwise:Option<Branch<'a>>
+}
+
+impl<'a> Parse for IfNode<'a>{
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut branches = vec![];
+        let mut otherwise = None;
+        loop{
+            input.parse::<Token![if]>()?;
+            let cond = Expr::parse_without_eager_brace(input)?;
+            branches.push((cond, input.parse::<Branch>()?));
+            if !input.peek(Token![else]){
+                break;
+            }
+            input.parse::<Token![else]>()?;
+            if !input.peek(Token![if]){
+                otherwise = Some(input.parse::<Branch>()?);
+                break;
+            }
+        }
+        Ok(Self{branches, otherwise})
+    }
+}
+
+impl<'a> IfNode<'a>{
+    pub fn collect_refs(&self, refs:&mut Vec<RefInfo>){
+        for (_, branch) in &self.branches{
+            branch.collect_refs(refs);
+        }
+        if let Some(branch) = &self.otherwise{
+            branch.collect_refs(refs);
+        }
+    }
+}
+
+impl<'a> ToTokens for IfNode<'a>{
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        // a missing `else` renders nothing
+        let otherwise = match &self.otherwise{
+            Some(branch)=>quote!(#branch),
+            None=>quote!(())
+        };
+        let count = self.branches.len() + 1;
+        let mut ts = either(count-1, count, otherwise);
+        for (index, (cond, branch)) in self.branches.iter().enumerate().rev(){
+            let value = either(index, count, quote!(#branch));
+            ts = quote!(if #cond { #value } else { #ts });
+        }
+        ts.to_tokens(tokens);
+    }
+}
+
+pub struct MatchArm<'a>{
+    pat:Pat,
+    guard:Option<Expr>,
+    branch:Branch<'a>
+}
+
+impl<'a> Parse for MatchArm<'a>{
+    fn parse(input: ParseStream) -> Result<Self> {
+        let leading_vert:Option<Token![|]> = input.parse()?;
+        let pat:Pat = input.parse()?;
+        let pat = if leading_vert.is_some() || (input.peek(Token![|]) && !input.peek(Token![||])){
+            let mut cases = syn::punctuated::Punctuated::new();
+            cases.push_value(pat);
+            while input.peek(Token![|]) && !input.peek(Token![||]){
+                cases.push_punct(input.parse::<Token![|]>()?);
+                cases.push_value(input.parse::<Pat>()?);
+            }
+            Pat::Or(syn::PatOr{
+                attrs:vec![],
+                leading_vert,
+                cases
+            })
+        }else{
+            pat
+        };
+        let guard = if input.peek(Token![if]){
+            input.parse::<Token![if]>()?;
+            Some(input.parse::<Expr>()?)
+        }else{
+            None
+        };
+        input.parse::<Token![=>]>()?;
+        let branch = input.parse::<Branch>()?;
+        if input.peek(Token![,]){
+            input.parse::<Token![,]>()?;
+        }
+        Ok(Self{pat, guard, branch})
+    }
+}
+
+/// `match expr { Pattern => <element/>, Pattern => { ... } }`
+pub struct MatchNode<'a>{
+    expr:Expr,
+    arms:Vec<MatchArm<'a>>
+}
+
+impl<'a> Parse for MatchNode<'a>{
+    fn parse(input: ParseStream) -> Result<Self> {
+        input.parse::<Token![match]>()?;
+        let expr = Expr::parse_without_eager_brace(input)?;
+        let content;
+        braced!(content in input);
+        let mut arms = vec![];
+        while !content.is_empty(){
+            arms.push(content.parse::<MatchArm>()?);
+        }
+        Ok(Self{expr, arms})
+    }
+}
+
+impl<'a> MatchNode<'a>{
+    pub fn collect_refs(&self, refs:&mut Vec<RefInfo>){
+        for arm in &self.arms{
+            arm.branch.collect_refs(refs);
+        }
+    }
+}
+
+impl<'a> ToTokens for MatchNode<'a>{
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let expr = &self.expr;
+        let count = self.arms.len();
+        let arms = self.arms.iter().enumerate().map(|(index, arm)|{
+            let pat = &arm.pat;
+            let guard = arm.guard.as_ref().map(|guard| quote!(if #guard));
+            let value = either(index, count, arm.branch.to_token_stream());
+            quote!(#pat #guard => { #value })
+        });
+        quote!(match #expr { #(#arms),* }).to_tokens(tokens);
+    }
+}
+
+/// `for pattern in iter { ... }`, rendered as a `Vec` of the branch markup
+pub struct ForNode<'a>{
+    pat:Pat,
+    expr:Expr,
+    branch:Branch<'a>
+}
+
+impl<'a> Parse for ForNode<'a>{
+    fn parse(input: ParseStream) -> Result<Self> {
+        input.parse::<Token![for]>()?;
+        let pat = input.parse::<Pat>()?;
+        input.parse::<Token![in]>()?;
+        let expr = Expr::parse_without_eager_brace(input)?;
+        let branch = input.parse::<Branch>()?;
+        Ok(Self{pat, expr, branch})
+    }
+}
+
+impl<'a> ForNode<'a>{
+    pub fn collect_refs(&self, refs:&mut Vec<RefInfo>){
+        let start = refs.len();
+        self.branch.collect_refs(refs);
+        for info in &mut refs[start..]{
+            info.in_loop = true;
+        }
+    }
+}
+
+impl<'a> ToTokens for ForNode<'a>{
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let ForNode{pat, expr, branch} = self;
+        quote!(
+            ::core::iter::IntoIterator::into_iter(#expr)
+                .map(|#pat| #branch)
+                .collect::<Vec<_>>()
+        ).to_tokens(tokens);
+    }
+}
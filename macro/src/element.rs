@@ -1,19 +1,28 @@
 //use std::sync::Arc;
 use proc_macro2::{TokenStream, Ident, Literal};
 //use proc_macro::TokenTree;
-use quote::{quote, ToTokens};
+use quote::{quote, quote_spanned, ToTokens};
 use syn::ext::IdentExt;
 use syn::{Block, Token, Result, punctuated::Punctuated};
-use syn::parse::{Parse, ParseStream};
+use syn::parse::{Parse, ParseStream, discouraged::Speculative};
 use proc_macro_error::abort;
 use crate::attributes::{Attributes, parse_attributes};
+use crate::control::{IfNode, MatchNode, ForNode};
+use crate::refs::{RefInfo, check_duplicates};
 //use crate::state::get_attributes;
 
 pub type TagName = Punctuated<Ident, Token![-]>;
 
+/// Elements that never have content, mirrors `flow_html::VOID_ELEMENTS`
+const VOID_ELEMENTS:[&str; 13] = [
+    "area", "base", "br", "col", "embed", "hr", "img",
+    "input", "link", "meta", "source", "track", "wbr"
+];
+
 pub trait TagNameString {
     fn to_string(&self)->String;
     fn is_custom_element(&self)->bool;
+    fn is_void_element(&self)->bool;
 }
 
 impl TagNameString for TagName{
@@ -34,6 +43,9 @@ impl TagNameString for TagName{
         let first = name.get(0..1).unwrap();
         first.to_uppercase() == first
     }
+    fn is_void_element(&self)->bool{
+        VOID_ELEMENTS.contains(&self.to_string().as_str())
+    }
 }
 
 pub struct Element<'a>{
@@ -48,14 +60,32 @@ impl<'a> Parse for Element<'a>{
         let tag = input.parse::<OpeningTag>()?;
         
         let mut children = None;
-        if !tag.self_closing{
+        if tag.name.is_void_element(){
+            // `<br>` is complete on its own, an explicit `<br></br>` pair is
+            // accepted when the closing tag follows right away
+            if !tag.self_closing && input.peek(Token![<]) && input.peek2(Token![/]){
+                let fork = input.fork();
+                let closing_tag = fork.parse::<ClosingTag>()?;
+                if closing_tag.name == tag.name{
+                    input.advance_to(&fork);
+                }
+            }
+        }else if !tag.self_closing{
             let nodes = input.parse::<Nodes>()?;
             if nodes.list.len() > 0{
                 children = Some(nodes);
             }
+            let closing_span = input.span();
             let closing_tag = input.parse::<ClosingTag>()?;
-            if closing_tag.name != tag.name{
-                abort!(span, format!("Closing tag is missing for '{}'", tag.name.to_string()));
+            if closing_tag.name.is_void_element() && closing_tag.name != tag.name{
+                // the content of a `<br>...</br>` pair was parsed as siblings
+                abort!(
+                    closing_span,
+                    format!("Void element '{}' cannot have children", closing_tag.name.to_string())
+                );
+            }
+            if closing_tag.name != tag.name || closing_tag.slot != tag.slot{
+                abort!(span, format!("Closing tag is missing for '{}'", tag.display_name()));
             }
         }
         //println!("=================== end: Element parsing ########################");
@@ -72,6 +102,21 @@ impl<'a> Element<'a>{
     fn is_custom_element(&self)->bool{
         self.tag.name.is_custom_element()
     }
+    pub fn collect_refs(&self, optional:bool, refs:&mut Vec<RefInfo>){
+        if let Some(attr) = self.tag.attributes.get_ref(){
+            refs.push(RefInfo{
+                name:attr.get_name(),
+                tag:self.tag.name.to_string(),
+                component:self.is_custom_element(),
+                optional,
+                in_loop:false,
+                span:attr.name.first().unwrap().span()
+            });
+        }
+        if let Some(children) = &self.children{
+            children.collect_refs(optional, refs);
+        }
+    }
     fn children_stream(&self)->TokenStream{
         match &self.children{
             Some(nodes)=>{
@@ -83,12 +128,44 @@ impl<'a> Element<'a>{
             }
         }
     }
+    /// Builder calls for the children of a component, `<slot:name>` elements
+    /// are returned separately as `.name(content)` calls
+    fn component_children(&self)->(TokenStream, Vec<TokenStream>){
+        let span = self.tag.name.first().unwrap().span();
+        let nodes = match &self.children{
+            Some(nodes)=>nodes,
+            None=>return (quote_spanned!(span=> .children(Option::<()>::None)), vec![])
+        };
+        let mut slots = vec![];
+        let mut children = vec![];
+        for node in &nodes.list{
+            match node{
+                Node::Element(el) if el.tag.slot.is_some()=>{
+                    let slot = el.tag.slot.as_ref().unwrap();
+                    let content = match &el.children{
+                        Some(nodes)=>nodes.get_tuples(),
+                        None=>quote!(())
+                    };
+                    slots.push(quote_spanned!(slot.span()=> .#slot(#content)));
+                }
+                node=>children.push(quote!(#node))
+            }
+        }
+        let children = if children.is_empty(){
+            quote_spanned!(span=> .children(Option::<()>::None))
+        }else{
+            let children = tuples(children);
+            quote_spanned!(span=> .children(Some(#children)))
+        };
+        (children, slots)
+    }
 }
 
 impl<'a> ToTokens for Element<'a>{
     fn to_tokens(&self, tokens: &mut TokenStream) {
-        //let mut properties:Vec<TokenStream> = vec![];
-        let children = self.children_stream();
+        if let Some(slot) = &self.tag.slot{
+            abort!(slot, format!("'<slot:{}>' must be a direct child of a component", slot));
+        }
         let el = if self.is_custom_element(){
             let name = &self.tag.name;
             /*
@@ -97,14 +174,24 @@ impl<'a> ToTokens for Element<'a>{
                 None=>Arc::new(vec![])
             };
             */
-            let mut properties = self.tag.attributes.to_properties();//names);
+            let properties = self.tag.attributes.to_properties();//names);
             //println!("properties: {:?}", properties);
-            properties.push(children);
-            quote!(#name {
-                #(#properties),*,
-                ..Default::default()
-            })
+            let (children, slots) = self.component_children();
+            // missing required props are reported at the tag, the whole
+            // chain starts and ends with its span so errors do not spread
+            // over the surrounding markup
+            let span = self.tag.name.first().unwrap().span();
+            let builder = quote_spanned!(span=> #name::builder());
+            let build = quote_spanned!(span=> .build());
+            quote!(
+                #builder
+                    #(#properties)*
+                    #children
+                    #(#slots)*
+                    #build
+            )
         }else{
+            let children = self.children_stream();
             let attributes = self.tag.attributes.to_token_stream();
             let tag = self.tag.name.to_string();
             let is_fragment = tag.len()==0;
@@ -124,9 +211,29 @@ impl<'a> ToTokens for Element<'a>{
 
 pub struct OpeningTag<'a>{
     pub name:TagName,
+    /// `<slot:name>` of a component
+    pub slot:Option<Ident>,
     pub self_closing:bool,
     pub attributes:Attributes<'a>
 }
+
+impl<'a> OpeningTag<'a>{
+    fn display_name(&self)->String{
+        match &self.slot{
+            Some(slot)=>format!("slot:{}", slot),
+            None=>self.name.to_string()
+        }
+    }
+}
+
+/// Parses the `:name` following a `slot` tag name
+fn parse_slot(name:&TagName, input:ParseStream)->Result<Option<Ident>>{
+    if name.to_string() == "slot" && input.peek(Token![:]){
+        input.parse::<Token![:]>()?;
+        return Ok(Some(input.call(Ident::parse_any)?));
+    }
+    Ok(None)
+}
 fn get_fragment_tag_name()->TagName{
     Punctuated::new()// Ident::new("x", Span::call_site())
 }
@@ -134,6 +241,7 @@ impl<'a> Parse for OpeningTag<'a>{
     fn parse(input: ParseStream) -> Result<Self> {
         let mut self_closing = false;
         let name;
+        let mut slot = None;
         let attributes;
         input.parse::<Token![<]>()?;
         if input.peek(Token![>]){
@@ -141,7 +249,11 @@ impl<'a> Parse for OpeningTag<'a>{
             attributes = Attributes::empty()
         }else{
             name = TagName::parse_separated_nonempty_with(input, syn::Ident::parse_any)?;
+            slot = parse_slot(&name, input)?;
             attributes = parse_attributes(input)?;
+            if slot.is_some() && !attributes.is_empty(){
+                abort!(name, "Slots cannot have attributes");
+            }
             if input.peek(Token![/]){
                 input.parse::<Token![/]>()?;
                 self_closing = true;
@@ -151,6 +263,7 @@ impl<'a> Parse for OpeningTag<'a>{
         input.parse::<Token![>]>()?;
         Ok(Self{
             name,
+            slot,
             self_closing,
             attributes
         })
@@ -158,22 +271,23 @@ impl<'a> Parse for OpeningTag<'a>{
 }
 
 pub struct ClosingTag{
-    pub name:TagName
+    pub name:TagName,
+    pub slot:Option<Ident>
 }
 
 impl Parse for ClosingTag{
     fn parse(input: ParseStream) -> Result<Self> {
         if input.is_empty() || !input.peek(Token![<]){
-            return Ok(Self{name:get_fragment_tag_name()});
+            return Ok(Self{name:get_fragment_tag_name(), slot:None});
         }
         input.parse::<Token![<]>()?;
         if input.is_empty() || input.peek2(Token![/]){
             //abort!(input.span(), format!("Closing tag is missing"));
-            return Ok(Self{name:get_fragment_tag_name()});
+            return Ok(Self{name:get_fragment_tag_name(), slot:None});
         }
         input.parse::<Token![/]>()?;
         if input.is_empty(){
-            return Ok(Self{name:get_fragment_tag_name()});
+            return Ok(Self{name:get_fragment_tag_name(), slot:None});
         }
         let name;
         if input.peek(Token![>]){
@@ -183,16 +297,18 @@ impl Parse for ClosingTag{
                 Ok(tag_name)=>tag_name,
                 Err(_e)=>{
                     //for closing tag validation making a empty close tag 
-                    return Ok(Self{name:get_fragment_tag_name()});
+                    return Ok(Self{name:get_fragment_tag_name(), slot:None});
                 }
             };
         }
+        let slot = parse_slot(&name, input)?;
         if input.is_empty() || !input.peek(Token![>]){
-            return Ok(Self{name:get_fragment_tag_name()});
+            return Ok(Self{name:get_fragment_tag_name(), slot:None});
         }
         input.parse::<Token![>]>()?;
         Ok(Self{
-            name
+            name,
+            slot
         })
     }
 }
@@ -202,27 +318,54 @@ pub struct Nodes<'a>{
 }
 
 impl<'a> Nodes<'a>{
+    pub fn from_element(element:Element<'a>)->Self{
+        Self{
+            list:vec![Node::Element(element)]
+        }
+    }
+    pub fn check_refs(&self){
+        let mut refs = vec![];
+        self.collect_refs(false, &mut refs);
+        check_duplicates(&refs);
+    }
+    /// Appends the `@name` elements of these nodes and their descendants to `refs`
+    pub fn collect_refs(&self, optional:bool, refs:&mut Vec<RefInfo>){
+        for node in &self.list{
+            match node{
+                Node::Element(el)=>el.collect_refs(optional, refs),
+                Node::If(node)=>node.collect_refs(refs),
+                Node::Match(node)=>node.collect_refs(refs),
+                Node::For(node)=>node.collect_refs(refs),
+                Node::Block(_) | Node::Literal(_)=>{}
+            }
+        }
+    }
     pub fn get_tuples(&self)->TokenStream{
-        if self.list.len() == 1{
-            let node = &self.list[0];
-            quote!{#node}
-        }else{
-            let mut group = vec![];
-            let list:Vec<TokenStream> = self.list.iter()
-                    .map(|item| quote!{#item})
-                    .collect();
-            for chunk in list.chunks(10){
-                group.push(quote!{ ( #(#chunk),* ) } );
-                if group.len() == 10{
-                    let combined = quote!{ ( #(#group),* ) };
-                    group = vec![];
-                    group.push(combined);
-                }
+        let list:Vec<TokenStream> = self.list.iter()
+                .map(|item| quote!{#item})
+                .collect();
+        tuples(list)
+    }
+}
+
+/// Groups rendered nodes into (nested) tuples of at most 10 items
+fn tuples(list:Vec<TokenStream>)->TokenStream{
+    if list.len() == 1{
+        let node = &list[0];
+        quote!{#node}
+    }else{
+        let mut group = vec![];
+        for chunk in list.chunks(10){
+            group.push(quote!{ ( #(#chunk),* ) } );
+            if group.len() == 10{
+                let combined = quote!{ ( #(#group),* ) };
+                group = vec![];
+                group.push(combined);
             }
-            
-            let children = quote!{(#(#group),*)};
-            quote!{#children}
         }
+        
+        let children = quote!{(#(#group),*)};
+        quote!{#children}
     }
 }
 
@@ -251,6 +394,9 @@ impl<'a> ToTokens for Nodes<'a>{
 pub enum Node<'a>{
     Element(Element<'a>),
     Block(Block),
+    If(IfNode<'a>),
+    Match(MatchNode<'a>),
+    For(ForNode<'a>),
     //TokenStream(proc_macro2::TokenStream)
     Literal(Literal)
 }
@@ -261,6 +407,12 @@ impl<'a> Parse for Node<'a>{
             Node::Element(input.parse::<Element>()?)
         }else if input.peek(syn::token::Brace){
             Node::Block(input.parse::<Block>()?)
+        }else if input.peek(Token![if]){
+            Node::If(input.parse::<IfNode>()?)
+        }else if input.peek(Token![match]){
+            Node::Match(input.parse::<MatchNode>()?)
+        }else if input.peek(Token![for]){
+            Node::For(input.parse::<ForNode>()?)
         }else{
             
             /*
@@ -306,6 +458,15 @@ impl<'a> ToTokens for Node<'a>{
             Node::Literal(el)=>{
                 el.to_tokens(tokens);
             }
+            Node::If(node)=>{
+                node.to_tokens(tokens);
+            }
+            Node::Match(node)=>{
+                node.to_tokens(tokens);
+            }
+            Node::For(node)=>{
+                node.to_tokens(tokens);
+            }
             Node::Block(block)=>{
                 if block.stmts.len() == 1{
                     let stm = &block.stmts[0];
@@ -2,7 +2,7 @@ use proc_macro::TokenStream;
 use syn::{
     DeriveInput,
     parse_macro_input,
-    parse::{ParseStream,Parse},
+    parse::{ParseStream,Parse,discouraged::Speculative},
     ext::IdentExt,
     Meta, NestedMeta
 };
@@ -10,27 +10,65 @@ use quote::quote;
 mod element;
 //mod state;
 mod attributes;
+mod control;
+mod refs;
+mod component;
+mod slots;
+mod builder;
 use element::Nodes;
 //use state::set_attributes;
 use attributes::{AttributeName, AttributeNameString};
-use proc_macro_error::proc_macro_error;
+use proc_macro_error::{proc_macro_error, abort};
 
 
 #[proc_macro]
 #[proc_macro_error]
 pub fn tree(input: TokenStream) -> TokenStream {
+    refs::begin(&input);
     let nodes =  parse_macro_input!(input as Nodes);
+    nodes.check_refs();
     let ts = quote!{#nodes};
     //println!("\n===========> Nodes Object tree <===========\n{}\n", ts.to_string());
     ts.into()
 }
 
+/// `html!{...}` markup, optionally preceded by `@refs`
+struct HtmlInput<'a>{
+    refs:bool,
+    nodes:Nodes<'a>
+}
+
+impl<'a> Parse for HtmlInput<'a> {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut refs = false;
+        if input.peek(syn::Token![@]) && input.peek2(syn::Ident){
+            let fork = input.fork();
+            fork.parse::<syn::Token![@]>()?;
+            if fork.parse::<syn::Ident>()? == "refs"{
+                input.advance_to(&fork);
+                refs = true;
+            }
+        }
+        Ok(HtmlInput{
+            refs,
+            nodes:input.parse::<Nodes>()?
+        })
+    }
+}
+
 #[proc_macro]
 #[proc_macro_error]
 pub fn html(input: TokenStream) -> TokenStream {
-    let nodes =  parse_macro_input!(input as Nodes);
+    refs::begin(&input);
+    let HtmlInput{refs, nodes} =  parse_macro_input!(input as HtmlInput);
+    nodes.check_refs();
     let ts = quote!{#nodes};
     //println!("\n===========> Nodes Object tree <===========\n{}\n", ts.to_string());
+    if refs{
+        let mut list = vec![];
+        nodes.collect_refs(false, &mut list);
+        return refs::refs_struct(ts, list).into();
+    }
     quote!({
         let elements = #ts;
 
@@ -41,7 +79,9 @@ pub fn html(input: TokenStream) -> TokenStream {
 #[proc_macro]
 #[proc_macro_error]
 pub fn html_str(input: TokenStream) -> TokenStream {
+    refs::begin(&input);
     let nodes =  parse_macro_input!(input as Nodes);
+    nodes.check_refs();
     let ts = quote!{#nodes};
     //println!("\n===========> Nodes Object tree <===========\n{}\n", ts.to_string());
     quote!({
@@ -52,25 +92,235 @@ pub fn html_str(input: TokenStream) -> TokenStream {
 }
 
 struct RenderableAttributes {
-    pub tag_name : String
+    pub tag_name : String,
+    /// Method rendering the content: `#[renderable(tag, view)]` or `view = method`
+    pub view : Option<syn::Ident>,
+    /// Case of the attribute names: `#[renderable(tag, rename_all = "kebab-case")]`
+    pub rename_all : Option<String>
 }
 
+const RENAME_RULES:[&str; 8] = [
+    "lowercase", "UPPERCASE", "PascalCase", "camelCase",
+    "snake_case", "SCREAMING_SNAKE_CASE", "kebab-case", "SCREAMING-KEBAB-CASE"
+];
+
 impl Parse for RenderableAttributes {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         let tag_name = AttributeName::parse_separated_nonempty_with(input, syn::Ident::parse_any)?;
+        let mut view = None;
+        let mut rename_all = None;
+        while input.peek(syn::Token![,]){
+            input.parse::<syn::Token![,]>()?;
+            let key = input.parse::<syn::Ident>()?;
+            match key.to_string().as_str(){
+                "view"=>{
+                    view = Some(if input.peek(syn::Token![=]){
+                        input.parse::<syn::Token![=]>()?;
+                        input.parse::<syn::Ident>()?
+                    }else{
+                        key
+                    });
+                }
+                "rename_all"=>{
+                    input.parse::<syn::Token![=]>()?;
+                    let rule = input.parse::<syn::LitStr>()?;
+                    if !RENAME_RULES.contains(&rule.value().as_str()){
+                        abort!(rule, format!("Unknown rename rule '{}', expected one of: {}", rule.value(), RENAME_RULES.join(", ")));
+                    }
+                    rename_all = Some(rule.value());
+                }
+                _=>abort!(key, format!("Unknown renderable option '{}', expected 'view' or 'rename_all'", key))
+            }
+        }
         Ok(RenderableAttributes{
-            tag_name : tag_name.to_string()
+            tag_name : tag_name.to_string(),
+            view,
+            rename_all
         })
     }
 }
 
+/// Attribute name of a snake_case field following a `rename_all` rule
+fn rename_field(name:&str, rule:&str)->String{
+    let words:Vec<&str> = name.split('_').filter(|word| !word.is_empty()).collect();
+    let capitalize = |word:&str|{
+        let mut chars = word.chars();
+        match chars.next(){
+            Some(first)=>first.to_uppercase().chain(chars).collect::<String>(),
+            None=>String::new()
+        }
+    };
+    match rule{
+        "lowercase"=>words.concat().to_lowercase(),
+        "UPPERCASE"=>words.concat().to_uppercase(),
+        "PascalCase"=>words.iter().map(|word| capitalize(word)).collect(),
+        "camelCase"=>words.iter().enumerate()
+            .map(|(index, word)| if index == 0 { word.to_string() } else { capitalize(word) })
+            .collect(),
+        "SCREAMING_SNAKE_CASE"=>words.join("_").to_uppercase(),
+        "kebab-case"=>words.join("-"),
+        "SCREAMING-KEBAB-CASE"=>words.join("-").to_uppercase(),
+        _=>words.join("_")
+    }
+}
+
+/// snake_case words of a PascalCase name
+fn snake_case(name:&str)->String{
+    let mut snake = String::new();
+    for (index, c) in name.chars().enumerate(){
+        if c.is_uppercase() && index > 0{
+            snake.push('_');
+        }
+        snake.extend(c.to_lowercase());
+    }
+    snake
+}
+
+/// `name = "..."` and `rename_all = "..."` of `#[attr(...)]` on an enum or variant
+fn enum_attr_option(attrs:&[syn::Attribute], key:&str)->Option<syn::LitStr>{
+    let mut value = None;
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("attr")){
+        let list = match attr.parse_meta(){
+            Ok(Meta::List(list))=>list,
+            Ok(meta)=>abort!(meta, "Expected `#[attr(...)]` with options"),
+            Err(err)=>abort!(err.span(), err.to_string())
+        };
+        for item in list.nested.iter(){
+            match item{
+                NestedMeta::Meta(Meta::NameValue(name_value)) if name_value.path.is_ident(key)=>{
+                    match &name_value.lit{
+                        syn::Lit::Str(lit)=>value = Some(lit.clone()),
+                        lit=>abort!(lit, format!("Expected a string: `{} = \"...\"`", key))
+                    }
+                }
+                item=>abort!(item, format!("Unknown attr option, expected `{} = \"...\"`", key))
+            }
+        }
+    }
+    value
+}
+
+/// `#[derive(IntoAttribute)]` on a unit enum writes the variant names in
+/// kebab-case, `#[attr(rename_all = "...")]` on the enum picks another case
+/// and `#[attr(name = "...")]` renames a single variant
+#[proc_macro_derive(IntoAttribute, attributes(attr))]
+#[proc_macro_error]
+pub fn into_attribute(item: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(item as DeriveInput);
+    let name = &ast.ident;
+    let (impl_generics, type_generics, where_clause) = ast.generics.split_for_impl();
+    let data = match &ast.data{
+        syn::Data::Enum(data)=>data,
+        _=>abort!(ast.ident, "`IntoAttribute` can only be derived for unit enums")
+    };
+    let rule = match enum_attr_option(&ast.attrs, "rename_all"){
+        Some(rule)=>{
+            if !RENAME_RULES.contains(&rule.value().as_str()){
+                abort!(rule, format!("Unknown rename rule '{}', expected one of: {}", rule.value(), RENAME_RULES.join(", ")));
+            }
+            rule.value()
+        }
+        None=>"kebab-case".to_string()
+    };
+    let mut arms = vec![];
+    for variant in data.variants.iter(){
+        if !matches!(variant.fields, syn::Fields::Unit){
+            abort!(variant, "`IntoAttribute` can only be derived for unit variants");
+        }
+        let ident = &variant.ident;
+        let value = match enum_attr_option(&variant.attrs, "name"){
+            Some(value)=>value.value(),
+            None=>rename_field(&snake_case(&ident.to_string()), &rule)
+        };
+        arms.push(quote!(#name::#ident=>#value));
+    }
+    quote!(
+        impl #impl_generics flow_html::IntoAttribute for #name #type_generics #where_clause {
+            fn to_attribute(&self)->Option<flow_html::AttributeText<'_>>{
+                Some(flow_html::AttributeText::Value(::std::borrow::Cow::Borrowed(match self{
+                    #(#arms),*
+                })))
+            }
+        }
+    ).into()
+}
+
+/// Options of a field given with `#[attr(...)]`
+#[derive(Default)]
+struct FieldOptions {
+    name : Option<String>,
+    /// Internal state, not rendered as an attribute
+    skip : bool,
+    /// Map of extra attributes rendered as `key="value"`, keys that are not
+    /// valid attribute names are skipped
+    flatten : bool,
+    /// Rendered as `data-{name}`
+    data : bool
+}
+
+impl FieldOptions {
+    fn from_attributes(attrs:&[syn::Attribute])->Self{
+        let mut options = FieldOptions::default();
+        for attr in attrs.iter().filter(|attr| attr.path.is_ident("attr")){
+            let list = match attr.parse_meta(){
+                Ok(Meta::List(list))=>list,
+                Ok(meta)=>abort!(meta, "Expected `#[attr(...)]` with options"),
+                Err(err)=>abort!(err.span(), err.to_string())
+            };
+            for item in list.nested.iter(){
+                match item{
+                    NestedMeta::Meta(Meta::NameValue(name_value)) if name_value.path.is_ident("name")=>{
+                        match &name_value.lit{
+                            syn::Lit::Str(value)=>options.name = Some(value.value()),
+                            lit=>abort!(lit, "Expected a string: `name = \"...\"`")
+                        }
+                    }
+                    NestedMeta::Meta(Meta::Path(path)) if path.is_ident("skip")=>options.skip = true,
+                    NestedMeta::Meta(Meta::Path(path)) if path.is_ident("flatten")=>options.flatten = true,
+                    NestedMeta::Meta(Meta::Path(path)) if path.is_ident("data")=>options.data = true,
+                    NestedMeta::Meta(meta) if meta.path().is_ident("default")=>{
+                        abort!(meta, "`#[attr]` only controls how a field is rendered, use `#[prop(default)]` or `#[prop(default = ...)]` for a default value")
+                    }
+                    item=>abort!(item, "Unknown attr option, expected `name = \"...\"`, `skip`, `flatten` or `data`")
+                }
+            }
+        }
+        if options.flatten && (options.skip || options.data || options.name.is_some()){
+            abort!(attrs[0], "`flatten` cannot be combined with other attr options");
+        }
+        if options.skip && (options.data || options.name.is_some()){
+            abort!(attrs[0], "`skip` cannot be combined with other attr options");
+        }
+        options
+    }
+}
+
+/// `#[component] fn Card(title:&str, children:impl Render)->impl Render`,
+/// used as `<Card title={"x"}>...</Card>` rendering only what the function returns.
+///
+/// `Render::render` only has `&self`, so string rendering clones every
+/// argument that is not `impl Render` and those must be `Clone`, take a
+/// reference (`&T`) otherwise. DOM rendering moves the arguments.
+///
+/// `key={...}`, `@ref` and `on:event` apply to the first element the
+/// component renders.
+#[proc_macro_attribute]
+#[proc_macro_error]
+pub fn component(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let func = parse_macro_input!(item as syn::ItemFn);
+    component::component(func).into()
+}
+
 #[proc_macro_attribute]
 //#[proc_macro_derive(Renderable)]
 #[proc_macro_error]
 pub fn renderable(attr: TokenStream, item: TokenStream) -> TokenStream {
     let renderable_attr = parse_macro_input!(attr as RenderableAttributes);
     let tag_name = renderable_attr.tag_name;
-    let format_str = format!("<{} {{}}>{{}}</{}>", tag_name, tag_name);
+    // `{}{}` is the separating space, written only when there are attributes
+    let format_str = format!("<{}{{}}{{}}>{{}}</{}>", tag_name, tag_name);
+    let open_str = format!("<{}{{}}{{}}>", tag_name);
+    let close_str = format!("</{}>", tag_name);
     //println!("renderable_attr: {:?}", tag_name);
     //let def:proc_macro2::TokenStream = item.clone().into();
     let ast = parse_macro_input!(item as DeriveInput);
@@ -90,7 +340,10 @@ pub fn renderable(attr: TokenStream, item: TokenStream) -> TokenStream {
     let mut field_ident_vec = vec![];
     let mut field_type_vec = vec![];
     let mut attrs_ts_vec = vec![];
+    let mut node_attrs_ts_vec = vec![];
     let mut field_names:Vec<String> = vec![];
+    let mut slot_fields = vec![];
+    let mut props = vec![];
             
     //let mut children_field_ts = quote!();
     if let syn::Data::Struct(syn::DataStruct {
@@ -105,113 +358,68 @@ pub fn renderable(attr: TokenStream, item: TokenStream) -> TokenStream {
             field_visibility_vec.push(&field.vis);
             field_type_vec.push(&field.ty);
             let mut attr_name = field_name.to_string();
-            if attr_name.eq("children"){
+            if slots::is_slot(&field.attrs){
+                let param = slots::slot_param(&field_name, &field.ty, &ast.generics);
+                props.push(builder::Prop::new(field_name.clone(), field.ty.clone(), builder::PropKind::Slot(param)));
+                slot_fields.push(field_name);
+                continue;
+            }
+            let options = FieldOptions::from_attributes(&field.attrs);
+            let kind = match builder::prop_kind(&field.attrs){
+                // internal state and extra attributes are optional
+                builder::PropKind::Required if options.skip || options.flatten=>builder::PropKind::Default(None),
+                kind=>kind
+            };
+            props.push(builder::Prop::new(field_name.clone(), field.ty.clone(), kind));
+            if attr_name.eq("children") || options.skip{
                 //has_children_field = true;
                 continue;
             }
-            field_names.push(attr_name.clone());
-            //let name: String = field_name.to_string();
-            //println!("\n\n----->name: {}, \ntype: {:?}, \nattrs: {:?}", field_name, field.ty, field.attrs);
-            //println!("\n\n----->name: {}, \ntype: {:?}", field_name, field.ty);
-            let mut attrs:Vec<_> = field.attrs.iter().collect();
-            
-            if attrs.len()>0{
-                let attr  = attrs.remove(0);
-                let meta = attr.parse_meta().unwrap();
-                match meta{
-                    Meta::List(list)=>{
-                        //println!("meta-list: {:#?}", list);
-                        //println!("meta-list.path: {:#?}", list.path.get_ident().unwrap().to_string());
-                        //println!("nested: {:?}", list.nested);
-                        for item in list.nested.iter(){
-                            if let NestedMeta::Meta(m) = item{
-                                if let Meta::NameValue(name_value) = m{
-                                    let key = name_value.path.get_ident().unwrap().to_string();
-                                    let value:String = match &name_value.lit{
-                                        syn::Lit::Int(v)=>v.to_string(),
-                                        syn::Lit::Str(v)=>v.value(),
-                                        syn::Lit::Bool(v)=>v.value().to_string(),
-                                        _=>"".to_string()
-                                    };
-                                    //println!("key: {}, value: {}", key, value);
-                                    if key.eq("name"){
-                                        attr_name = value;
-                                    }
-                                }
-                            }
+            if options.flatten{
+                // keys are runtime data, ones that are not attribute names are skipped
+                attrs_ts_vec.push(quote!(
+                    for (key, value) in &self.#field_name{
+                        let key = key.to_string();
+                        if !flow_html::attribute::is_valid_attribute_name(&key){
+                            continue;
                         }
-
-                    }
-                    _=>{
-                        
-                    }
-                }
-            }
-            
-            let field_type = match &field.ty {
-                syn::Type::Path(a)=>{
-                    match a.path.get_ident(){
-                        Some(a)=>a.to_string(),
-                        None=>{
-                            if a.path.segments.len() > 0 {
-                                a.path.segments[0].ident.to_string()
-                            }else{
-                                "".to_string()
-                            }
+                        if let Some(attr) = flow_html::IntoAttribute::render_attribute(value, &key){
+                            attrs.push(attr);
                         }
                     }
-                }
-                syn::Type::Reference(a)=>{
-                    match a.elem.as_ref(){
-                        syn::Type::Path(a)=>{
-                            match a.path.get_ident(){
-                                Some(a)=>a.to_string(),
-                                None=>{
-                                    if a.path.segments.len() > 0 {
-                                        a.path.segments[0].ident.to_string()
-                                    }else{
-                                        "".to_string()
-                                    }
-                                }
-                            }
+                ));
+                node_attrs_ts_vec.push(quote!(
+                    for (key, value) in &self.#field_name{
+                        let key = key.to_string();
+                        if flow_html::attribute::is_valid_attribute_name(&key){
+                            flow_html::IntoAttribute::set_attribute(value, &ctx.backend, &el, &key)?;
                         }
-                        _=>"".to_string()
-                    }
-                }
-                _=>"".to_string()
-            };
-
-            if field_type.eq("bool"){
-                let fmt_str = format!("{}", attr_name);
-                attrs_ts_vec.push(quote!(
-                    if self.#field_name{
-                        attrs.push(#fmt_str.to_string());
                     }
                 ));
-            }else{
-                let fmt_str = format!("{}=\"{{}}\"", attr_name);
-                let mut borrow = quote!();
-                if field_type.eq("String"){
-                    borrow = quote!(&);
-                }
-                if field_type.eq("Option"){
-                    attrs_ts_vec.push(quote!(
-                        match &self.#field_name{
-                            Some(value)=>{
-                                attrs.push(format!(#fmt_str, flow_html::escape_attr(value)));
-                            }
-                            None=>{
-
-                            }
-                        }
-                    ));
-                }else{
-                    attrs_ts_vec.push(quote!(
-                        attrs.push(format!(#fmt_str, flow_html::escape_attr(#borrow self.#field_name)));
-                    ));
-                }
-                
+                continue;
+            }
+            if let Some(rule) = &renderable_attr.rename_all{
+                attr_name = rename_field(&attr_name, rule);
             }
+            if let Some(name) = options.name{
+                attr_name = name;
+            }
+            if options.data{
+                attr_name = format!("data-{}", attr_name);
+            }
+            field_names.push(attr_name.clone());
+            //let name: String = field_name.to_string();
+            //println!("\n\n----->name: {}, \ntype: {:?}, \nattrs: {:?}", field_name, field.ty, field.attrs);
+            //println!("\n\n----->name: {}, \ntype: {:?}", field_name, field.ty);
+            // any type implementing `IntoAttribute`, whatever it is called
+            attrs_ts_vec.push(quote!(
+                if let Some(attr) = flow_html::IntoAttribute::render_attribute(&self.#field_name, #attr_name){
+                    attrs.push(attr);
+                }
+            ));
+            node_attrs_ts_vec.push(quote!(
+                flow_html::IntoAttribute::set_attribute(&self.#field_name, &ctx.backend, &el, #attr_name)?;
+            ));
             
         }
         //if !has_children_field{
@@ -222,19 +430,99 @@ pub fn renderable(attr: TokenStream, item: TokenStream) -> TokenStream {
     }
 
     //set_attributes(struct_name.to_string(), field_names);
+    // the view gets the children separately, borrowed for string output
+    // and owned for the DOM
+    // followed by the slots in declaration order
+    let (render_ts, children_node_ts) = match &renderable_attr.view{
+        Some(view)=>(
+            quote!(
+                let attr = self.get_attributes();
+                let space = if attr.is_empty(){ "" }else{ " " };
+                write!(w, #open_str, space, attr)?;
+                flow_html::Render::render(&self.#view(
+                    flow_html::Borrowed(&self.children)
+                    #(, flow_html::Borrowed(&self.#slot_fields))*
+                ), w)?;
+                write!(w, #close_str)
+            ),
+            quote!(
+                let children = self.children.take();
+                #(let #slot_fields = self.#slot_fields.take();)*
+                flow_html::Render::render_node(self.#view(children #(, #slot_fields)*), &mut el, ctx)?;
+            )
+        ),
+        None=>(
+            quote!(
+                let attr = self.get_attributes();
+                let space = if attr.is_empty(){ "" }else{ " " };
+                let children = self.get_children();
+                write!(w, #format_str, space, attr, children)
+            ),
+            quote!(
+                #(
+                    if let Some(slot) = self.#slot_fields{
+                        slot.render_node(&mut el, ctx)?;
+                    }
+                )*
+                if let Some(children) = self.children{
+                    children.render_node(&mut el, ctx)?;
+                }
+            )
+        )
+    };
+    // without a view, slots are rendered before the children
+    let slots_html_ts = quote!(
+        #(
+            if let Some(slot) = &self.#slot_fields{
+                html.push_str(&slot.html());
+            }
+        )*
+    );
+    let hidden = builder::hidden_props();
+    let hidden_idents:Vec<_> = hidden.iter().map(|prop| prop.ident.clone()).collect();
+    let hidden_types:Vec<_> = hidden.iter().map(|prop| prop.ty.clone()).collect();
+    props.extend(hidden);
+    let builder_ts = builder::builder(&syn::parse_quote!(pub), struct_name, &ast.generics, &props);
+    let render_node_ts = if cfg!(feature = "dom"){
+        quote!(
+            #[allow(unused_mut)]
+            fn render_node<__B:flow_html::dom::DomBackend>(
+                mut self,
+                parent:&mut __B::Element,
+                ctx:&mut flow_html::dom::Context<__B>
+            )->flow_html::ElementResult<()>{
+                let mut el = ctx.backend.create_element(#tag_name)?;
+                #(#node_attrs_ts_vec)*
+                if let Some((key, value)) = self.__reff{
+                    ctx.set_ref(&el, key, value)?;
+                }
+                if let Some(key) = &self.__key{
+                    ctx.backend.set_key(&el, key)?;
+                }
+                for listener in ::std::mem::take(&mut self.__listeners){
+                    ctx.add_listener(&el, listener);
+                }
+                #children_node_ts
+                ctx.backend.append_child(parent, &ctx.backend.element_node(&el))?;
+                Ok(())
+            }
+        )
+    }else{
+        quote!()
+    };
     let ts = quote!(
-        #[derive(Debug, Default)]
+        #[derive(Debug)]
         pub struct #struct_name #struct_params #where_clause {
             #( #field_visibility_vec #field_ident_vec : #field_type_vec ),*,
+            #( pub #hidden_idents : #hidden_types, )*
             //#children_field_ts
         }
 
         impl #impl_generics flow_html::Render for #struct_name #type_generics #where_clause {
             fn render<W:core::fmt::Write>(&self, w:&mut W)->core::fmt::Result{
-                let attr = self.get_attributes();
-                let children = self.get_children();
-                write!(w, #format_str, attr, children)
+                #render_ts
             }
+            #render_node_ts
         }
         impl #impl_generics flow_html::ElementDefaults for #struct_name #type_generics #where_clause {
             fn _get_attributes(&self)->String{
@@ -243,16 +531,15 @@ pub fn renderable(attr: TokenStream, item: TokenStream) -> TokenStream {
                 attrs.join(" ")
             }
             fn _get_children(&self)->String{
-                match &self.children{
-                    Some(children)=>{
-                        children.html()
-                    }
-                    None=>{
-                        "".to_string()
-                    }
+                let mut html = String::new();
+                #slots_html_ts
+                if let Some(children) = &self.children{
+                    html.push_str(&children.html());
                 }
+                html
             }
         }
+        #builder_ts
     );
     //println!("\n===========> element ts: <===========\n{}", ts);
     ts.into()
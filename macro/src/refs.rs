@@ -0,0 +1,157 @@
+use std::cell::Cell;
+use proc_macro2::{TokenStream, Ident, Span};
+use quote::quote;
+use proc_macro_error::abort;
+
+thread_local!{
+    /// Hash of the markup being expanded and the number of refs generated for it
+    static REF_SEED:Cell<(u64, u32)> = const { Cell::new((0, 0)) };
+}
+
+/// 64 bit FNV-1a, stable across compilers unlike `DefaultHasher`
+fn fnv1a(bytes:&[u8], mut hash:u64)->u64{
+    for byte in bytes{
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// Seeds anonymous ref values with the macro input and its call site, so
+/// a given invocation always expands to the same values and identical
+/// markup in two places does not
+pub fn begin(input:&proc_macro::TokenStream){
+    let site = proc_macro::Span::call_site();
+    let location = format!("{}:{}:{}", site.file(), site.line(), site.column());
+    let seed = fnv1a(input.to_string().as_bytes(), 0xcbf29ce484222325);
+    let seed = fnv1a(location.as_bytes(), seed);
+    REF_SEED.with(|state| state.set((seed, 0)));
+}
+
+/// Value of the next anonymous `@name` ref of the current markup
+pub fn next_ref_value()->String{
+    let (seed, index) = REF_SEED.with(|state|{
+        let (seed, index) = state.get();
+        state.set((seed, index + 1));
+        (seed, index)
+    });
+    let hash = fnv1a(&index.to_le_bytes(), seed);
+    format!("ref_{:08x}", (hash ^ (hash >> 32)) as u32)
+}
+
+/// Rejects a ref name used twice, except in separate conditional branches,
+/// and refs inside loops, which would name every item
+pub fn check_duplicates(refs:&[RefInfo]){
+    for (index, info) in refs.iter().enumerate(){
+        if info.in_loop{
+            abort!(info.span, format!("Ref '@{}' inside a `for` loop would be set by every item", info.name);
+                help = "give the items keys and look them up from a ref on the parent element");
+        }
+        let duplicate = refs[..index].iter()
+            .any(|other| other.name == info.name && !(other.optional && info.optional));
+        if duplicate{
+            abort!(info.span, format!("Duplicate ref '@{}'", info.name));
+        }
+    }
+}
+
+/// Element marked with `@name`, collected for `html!{@refs ...}`
+pub struct RefInfo{
+    pub name:String,
+    pub tag:String,
+    /// Declared with a component (`<MyComponent @name/>`)
+    pub component:bool,
+    /// Inside an `if`, `match` or `for` node, so it may not be rendered
+    pub optional:bool,
+    /// Inside a `for` node
+    pub in_loop:bool,
+    pub span:Span
+}
+
+impl RefInfo{
+    fn field(&self)->Ident{
+        Ident::new(&self.name.replace('-', "_"), self.span)
+    }
+
+    /// `web_sys` type of the referenced element
+    fn element_type(&self)->TokenStream{
+        if self.component{
+            return quote!(flow_html::web_sys::Element);
+        }
+        let name = match self.tag.as_str(){
+            "a"=>"HtmlAnchorElement",
+            "button"=>"HtmlButtonElement",
+            "canvas"=>"HtmlCanvasElement",
+            "div"=>"HtmlDivElement",
+            "form"=>"HtmlFormElement",
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6"=>"HtmlHeadingElement",
+            "img"=>"HtmlImageElement",
+            "input"=>"HtmlInputElement",
+            "label"=>"HtmlLabelElement",
+            "li"=>"HtmlLiElement",
+            "ol"=>"HtmlOListElement",
+            "option"=>"HtmlOptionElement",
+            "p"=>"HtmlParagraphElement",
+            "select"=>"HtmlSelectElement",
+            "span"=>"HtmlSpanElement",
+            "table"=>"HtmlTableElement",
+            "textarea"=>"HtmlTextAreaElement",
+            "ul"=>"HtmlUListElement",
+            _=>"HtmlElement"
+        };
+        let ident = Ident::new(name, Span::call_site());
+        quote!(flow_html::web_sys::#ident)
+    }
+}
+
+/// `Refs` struct holding the rendered `Html` and a typed field per `@name`
+pub fn refs_struct(nodes:TokenStream, refs:Vec<RefInfo>)->TokenStream{
+    let mut fields = vec![];
+    let mut values = vec![];
+    for (index, info) in refs.iter().enumerate(){
+        // conditional refs sharing a name share the field
+        if refs[..index].iter().any(|other| other.name == info.name){
+            continue;
+        }
+        if info.name == "html"{
+            abort!(info.span, "'@html' is reserved for the rendered `Html`");
+        }
+        let field = info.field();
+        let name = &info.name;
+        let ty = info.element_type();
+        if info.optional{
+            fields.push(quote!(pub #field: Option<#ty>));
+            values.push(quote!(#field: match html.hook::<#ty>(#name){
+                Ok(element)=>Some(element),
+                Err(flow_html::HookError::Missing(_))=>None,
+                Err(err)=>return Err(err.into())
+            }));
+        }else{
+            fields.push(quote!(pub #field: #ty));
+            values.push(quote!(#field: html.hook::<#ty>(#name)?));
+        }
+    }
+
+    quote!({
+        /// Elements marked with `@name` in the markup
+        pub struct Refs{
+            pub html: flow_html::Html,
+            #(#fields,)*
+        }
+        impl ::core::ops::Deref for Refs{
+            type Target = flow_html::Html;
+            fn deref(&self)->&flow_html::Html{
+                &self.html
+            }
+        }
+        fn refs(html: flow_html::Html)->flow_html::ElementResult<Refs>{
+            Ok(Refs{
+                #(#values,)*
+                html
+            })
+        }
+        let elements = #nodes;
+
+        elements.render_tree().and_then(refs)
+    })
+}
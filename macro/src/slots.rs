@@ -0,0 +1,31 @@
+use proc_macro2::Ident;
+use syn::{Attribute, Generics, Type, PathArguments, GenericArgument};
+use proc_macro_error::abort;
+
+/// `#[slot] header:Option<H>` field, filled by `<slot:header>` in `tree!`
+pub fn is_slot(attrs:&[Attribute])->bool{
+    attrs.iter().any(|attr| attr.path.is_ident("slot"))
+}
+
+/// The generic parameter `H` of an `Option<H>` slot field
+pub fn slot_param(field:&Ident, ty:&Type, generics:&Generics)->Ident{
+    let param = match ty{
+        Type::Path(path) if path.qself.is_none()=>{
+            let segment = path.path.segments.last().unwrap();
+            match &segment.arguments{
+                PathArguments::AngleBracketed(args) if segment.ident == "Option" && args.args.len() == 1=>{
+                    match &args.args[0]{
+                        GenericArgument::Type(Type::Path(inner))=>inner.path.get_ident().cloned(),
+                        _=>None
+                    }
+                }
+                _=>None
+            }
+        }
+        _=>None
+    };
+    match param{
+        Some(param) if generics.type_params().any(|p| p.ident == param)=>param,
+        _=>abort!(ty, format!("Slot '{}' must be an `Option<T>` of a generic `T:Render` parameter", field))
+    }
+}
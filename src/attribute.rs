@@ -0,0 +1,257 @@
+//! Conversion of values into html attributes.
+//!
+//! The attributes of plain elements, `#[renderable]` fields and the values of
+//! [`AttributeValue`] are written through [`IntoAttribute`], so a value renders
+//! the same whatever its type is called and wherever it is used: `true` gives
+//! a bare `name`, `false` and `None` leave the attribute out and everything
+//! else is written as `name="value"`.
+//!
+//! Unit enums get a kebab-case value with `#[derive(IntoAttribute)]`:
+//!
+//! ```ignore
+//! #[derive(IntoAttribute)]
+//! enum Size{ Small, ExtraLarge }   // "small", "extra-large"
+//! ```
+use std::borrow::Cow;
+use std::rc::Rc;
+use crate::escape::escape_attr;
+use crate::signal::{Signal, Reactive};
+use crate::AttributeValue;
+#[cfg(feature = "dom")]
+use crate::dom::DomBackend;
+#[cfg(feature = "dom")]
+use crate::error::ElementResult;
+
+/// Whether `name` can be written as an attribute name: not empty and free
+/// of whitespace, control characters, quotes, `>`, `/` and `=`
+pub fn is_valid_attribute_name(name:&str)->bool{
+    !name.is_empty() && !name.chars().any(|c|{
+        c.is_whitespace() || c.is_control() || matches!(c, '"' | '\'' | '>' | '/' | '=' | '<')
+    })
+}
+
+/// Rendered form of an attribute
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttributeText<'a>{
+    /// Written without a value, e.g. `disabled`
+    Flag,
+    Value(Cow<'a, str>)
+}
+
+impl AttributeText<'_>{
+    pub fn into_owned(self)->AttributeText<'static>{
+        match self{
+            AttributeText::Flag=>AttributeText::Flag,
+            AttributeText::Value(value)=>AttributeText::Value(Cow::Owned(value.into_owned()))
+        }
+    }
+}
+
+/// Value that can be written as an html attribute
+pub trait IntoAttribute{
+    /// `None` leaves the attribute out
+    fn to_attribute(&self)->Option<AttributeText<'_>>;
+
+    /// Value kept by a plain element for the attribute, borrowing `self`
+    fn attribute_value(&self)->AttributeValue<'_>{
+        AttributeValue::Text(self.to_attribute())
+    }
+
+    /// Value kept by a plain element for `name={value}` in `tree!`
+    fn into_attribute_value<'a>(self)->AttributeValue<'a> where Self:Sized + 'a{
+        AttributeValue::Text(self.to_attribute().map(AttributeText::into_owned))
+    }
+
+    /// `name` or `name="value"` with the value escaped
+    fn render_attribute(&self, name:&str)->Option<String>{
+        Some(match self.to_attribute()?{
+            AttributeText::Flag=>name.to_string(),
+            AttributeText::Value(value)=>format!("{}=\"{}\"", name, escape_attr(value))
+        })
+    }
+
+    /// Sets the attribute on `element`, flags get the value `"true"`
+    #[cfg(feature = "dom")]
+    fn set_attribute<B:DomBackend>(&self, backend:&B, element:&B::Element, name:&str)->ElementResult<()>{
+        match self.to_attribute(){
+            Some(AttributeText::Flag)=>backend.set_attribute(element, name, "true"),
+            Some(AttributeText::Value(value))=>backend.set_attribute(element, name, &value),
+            None=>Ok(())
+        }
+    }
+}
+
+impl IntoAttribute for bool{
+    fn to_attribute(&self)->Option<AttributeText<'_>>{
+        self.then_some(AttributeText::Flag)
+    }
+    fn attribute_value(&self)->AttributeValue<'_>{
+        AttributeValue::Bool(*self)
+    }
+    fn into_attribute_value<'a>(self)->AttributeValue<'a>{
+        AttributeValue::Bool(self)
+    }
+}
+
+impl IntoAttribute for str{
+    fn to_attribute(&self)->Option<AttributeText<'_>>{
+        Some(AttributeText::Value(Cow::Borrowed(self)))
+    }
+    fn attribute_value(&self)->AttributeValue<'_>{
+        AttributeValue::Str(self)
+    }
+}
+
+impl IntoAttribute for String{
+    fn to_attribute(&self)->Option<AttributeText<'_>>{
+        Some(AttributeText::Value(Cow::Borrowed(self)))
+    }
+    fn attribute_value(&self)->AttributeValue<'_>{
+        AttributeValue::Str(self)
+    }
+    fn into_attribute_value<'a>(self)->AttributeValue<'a>{
+        AttributeValue::Owned(self)
+    }
+}
+
+impl IntoAttribute for Cow<'_, str>{
+    fn to_attribute(&self)->Option<AttributeText<'_>>{
+        Some(AttributeText::Value(Cow::Borrowed(self)))
+    }
+    fn attribute_value(&self)->AttributeValue<'_>{
+        AttributeValue::Str(self)
+    }
+    fn into_attribute_value<'a>(self)->AttributeValue<'a> where Self:'a{
+        AttributeValue::Cow(self)
+    }
+}
+
+impl IntoAttribute for char{
+    fn to_attribute(&self)->Option<AttributeText<'_>>{
+        Some(AttributeText::Value(Cow::Owned(self.to_string())))
+    }
+    fn attribute_value(&self)->AttributeValue<'_>{
+        AttributeValue::from(*self)
+    }
+    fn into_attribute_value<'a>(self)->AttributeValue<'a>{
+        AttributeValue::from(self)
+    }
+}
+
+macro_rules! impl_number {
+    ($($ty:ty),*) => {
+        $(
+            impl IntoAttribute for $ty{
+                fn to_attribute(&self)->Option<AttributeText<'_>>{
+                    Some(AttributeText::Value(Cow::Owned(self.to_string())))
+                }
+                fn attribute_value(&self)->AttributeValue<'_>{
+                    AttributeValue::from(*self)
+                }
+                fn into_attribute_value<'a>(self)->AttributeValue<'a>{
+                    AttributeValue::from(self)
+                }
+            }
+        )*
+    };
+}
+
+impl_number!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64);
+
+impl<T:IntoAttribute> IntoAttribute for Option<T>{
+    fn to_attribute(&self)->Option<AttributeText<'_>>{
+        self.as_ref()?.to_attribute()
+    }
+    fn attribute_value(&self)->AttributeValue<'_>{
+        AttributeValue::Optional(self.as_ref().map(|value| Box::new(value.attribute_value())))
+    }
+    fn into_attribute_value<'a>(self)->AttributeValue<'a> where Self:'a{
+        AttributeValue::Optional(self.map(|value| Box::new(value.into_attribute_value())))
+    }
+}
+
+impl<T:IntoAttribute + ?Sized> IntoAttribute for &T{
+    fn to_attribute(&self)->Option<AttributeText<'_>>{
+        (**self).to_attribute()
+    }
+    fn attribute_value(&self)->AttributeValue<'_>{
+        (**self).attribute_value()
+    }
+    /// Keeps borrowing the referenced value instead of copying it
+    fn into_attribute_value<'a>(self)->AttributeValue<'a> where Self:'a{
+        T::attribute_value(self)
+    }
+}
+
+impl<T:IntoAttribute + ?Sized> IntoAttribute for Box<T>{
+    fn to_attribute(&self)->Option<AttributeText<'_>>{
+        (**self).to_attribute()
+    }
+}
+
+impl<T:IntoAttribute + ?Sized> IntoAttribute for Rc<T>{
+    fn to_attribute(&self)->Option<AttributeText<'_>>{
+        (**self).to_attribute()
+    }
+}
+
+/// Class lists: the values joined with spaces, omitted values are skipped and
+/// an empty list leaves the attribute out
+impl<T:IntoAttribute> IntoAttribute for [T]{
+    fn to_attribute(&self)->Option<AttributeText<'_>>{
+        let values:Vec<Cow<'_, str>> = self.iter()
+            .filter_map(|item| match item.to_attribute()?{
+                AttributeText::Value(value)=>Some(value),
+                AttributeText::Flag=>None
+            })
+            .collect();
+        if values.is_empty(){
+            return None;
+        }
+        Some(AttributeText::Value(Cow::Owned(values.join(" "))))
+    }
+}
+
+impl<T:IntoAttribute> IntoAttribute for Vec<T>{
+    fn to_attribute(&self)->Option<AttributeText<'_>>{
+        self.as_slice().to_attribute()
+    }
+}
+
+impl<T:IntoAttribute, const N:usize> IntoAttribute for [T; N]{
+    fn to_attribute(&self)->Option<AttributeText<'_>>{
+        self.as_slice().to_attribute()
+    }
+}
+
+/// Follows the signal when used on a plain element, see [`crate::signal`]
+impl<T:std::fmt::Display+'static> IntoAttribute for Signal<T>{
+    fn to_attribute(&self)->Option<AttributeText<'_>>{
+        Some(AttributeText::Value(Cow::Owned(self.current())))
+    }
+    fn attribute_value(&self)->AttributeValue<'_>{
+        AttributeValue::Reactive(Box::new(self.clone()))
+    }
+    fn into_attribute_value<'a>(self)->AttributeValue<'a>{
+        AttributeValue::Reactive(Box::new(self))
+    }
+}
+
+impl IntoAttribute for AttributeValue<'_>{
+    fn into_attribute_value<'a>(self)->AttributeValue<'a> where Self:'a{
+        self
+    }
+    fn to_attribute(&self)->Option<AttributeText<'_>>{
+        match self{
+            AttributeValue::Bool(value)=>value.to_attribute(),
+            AttributeValue::Str(value)=>value.to_attribute(),
+            AttributeValue::Owned(value)=>value.to_attribute(),
+            AttributeValue::Cow(value)=>value.to_attribute(),
+            AttributeValue::Int(value)=>value.to_attribute(),
+            AttributeValue::Float(value)=>value.to_attribute(),
+            AttributeValue::Text(text)=>text.clone(),
+            AttributeValue::Optional(value)=>value.as_deref()?.to_attribute(),
+            AttributeValue::Reactive(value)=>Some(AttributeText::Value(Cow::Owned(value.current())))
+        }
+    }
+}
@@ -0,0 +1,75 @@
+//! Two-way form bindings declared with `bind:value={state}` and `bind:checked={state}`.
+//!
+//! The bound state provides the initial `value`/`checked` of the control and
+//! receives the user's edits from its `input`/`change` events. Signals also
+//! push changes made from Rust back into the control.
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::rc::Rc;
+use crate::signal::{Signal, Subscription};
+
+/// State a form control reads from and writes its edits back to
+pub trait Bindable<T>{
+    fn get(&self)->T;
+    fn set(&self, value:T);
+    /// Calls `f` with values set from Rust, `None` when changes are not observable
+    fn watch(&self, _f:Box<dyn Fn(T)>)->Option<Subscription>{
+        None
+    }
+}
+
+impl<T:Clone+'static> Bindable<T> for Signal<T>{
+    fn get(&self)->T{
+        self.with_untracked(T::clone)
+    }
+    fn set(&self, value:T){
+        Signal::set(self, value)
+    }
+    fn watch(&self, f:Box<dyn Fn(T)>)->Option<Subscription>{
+        Some(self.subscribe(move |value| f(value.clone())))
+    }
+}
+
+impl<T:Clone> Bindable<T> for Rc<RefCell<T>>{
+    fn get(&self)->T{
+        self.borrow().clone()
+    }
+    fn set(&self, value:T){
+        *self.borrow_mut() = value;
+    }
+}
+
+impl<T:Copy> Bindable<T> for Rc<Cell<T>>{
+    fn get(&self)->T{
+        Cell::get(self)
+    }
+    fn set(&self, value:T){
+        Cell::set(self, value)
+    }
+}
+
+/// Form control property bound to Rust state
+pub enum FormBinding{
+    /// `bind:value`, updated on `input` events
+    Value(Box<dyn Bindable<String>>),
+    /// `bind:checked`, updated on `change` events
+    Checked(Box<dyn Bindable<bool>>)
+}
+
+impl FormBinding{
+    pub fn value<S:Bindable<String>+'static>(state:S)->Self{
+        FormBinding::Value(Box::new(state))
+    }
+    pub fn checked<S:Bindable<bool>+'static>(state:S)->Self{
+        FormBinding::Checked(Box::new(state))
+    }
+}
+
+impl fmt::Debug for FormBinding{
+    fn fmt(&self, f:&mut fmt::Formatter<'_>)->fmt::Result{
+        match self{
+            FormBinding::Value(state)=>f.debug_tuple("Value").field(&state.get()).finish(),
+            FormBinding::Checked(state)=>f.debug_tuple("Checked").field(&state.get()).finish()
+        }
+    }
+}
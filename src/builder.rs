@@ -0,0 +1,37 @@
+//! Typestate markers of the builders generated by `#[renderable]` and
+//! `#[component]`. `Name::builder()` starts with a [`PropUnset`] parameter per
+//! prop without `#[prop(default)]`, its setter turns it into [`PropSet`] and
+//! `build()` only accepts unset props whose type is an [`OptionalProp`].
+
+/// Prop that has been given
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PropSet;
+
+/// Prop that has not been given
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PropUnset;
+
+/// Prop types that may be left out, the prop is then [`OptionalProp::unset`].
+///
+/// The type decides rather than its name, so `type Flag = bool` is optional
+/// as well. Other props are required unless they have `#[prop(default)]`.
+#[diagnostic::on_unimplemented(
+    message = "missing required prop of type `{Self}`",
+    label = "a required prop is not given",
+    note = "only `OptionalProp` types (`bool`, `Option<T>`) and `#[prop(default)]` props can be left out"
+)]
+pub trait OptionalProp{
+    fn unset()->Self;
+}
+
+impl OptionalProp for bool{
+    fn unset()->Self{
+        false
+    }
+}
+
+impl<T> OptionalProp for Option<T>{
+    fn unset()->Self{
+        None
+    }
+}
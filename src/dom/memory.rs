@@ -0,0 +1,430 @@
+use std::rc::{Rc, Weak};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::utils::{ElementResult, Error};
+use crate::escape::{escape_attr, escape_html};
+use crate::is_void_element;
+use super::{DomBackend, HookCast, Event, EventHandler, MemoryEvent};
+
+static LISTENER_ID:AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NodeKind{
+    Element{
+        tag:String,
+        attributes:BTreeMap<String, String>
+    },
+    Text(String),
+    /// Trusted markup inserted via [`DomBackend::append_html`]
+    Html(String),
+    /// Document fragment from [`DomBackend::fragment`], inserting it moves
+    /// its children instead
+    Fragment
+}
+
+struct NodeData{
+    kind:NodeKind,
+    parent:Weak<RefCell<NodeData>>,
+    children:Vec<Node>,
+    key:Option<String>,
+    /// `value` / `checked` properties, once they differ from the attributes
+    value:Option<String>,
+    checked:Option<bool>,
+    listeners:Vec<(u64, String, Rc<RefCell<EventHandler>>)>
+}
+
+/// Shared handle to a node of an in-memory document, clones refer to the same node
+#[derive(Clone)]
+pub struct Node(Rc<RefCell<NodeData>>);
+
+impl Node{
+    fn new(kind:NodeKind)->Self{
+        Self(Rc::new(RefCell::new(NodeData{
+            kind,
+            parent:Weak::new(),
+            children:vec![],
+            key:None,
+            value:None,
+            checked:None,
+            listeners:vec![]
+        })))
+    }
+    pub fn element(tag:&str)->Self{
+        Self::new(NodeKind::Element{
+            tag:tag.to_string(),
+            attributes:BTreeMap::new()
+        })
+    }
+    pub fn text(data:&str)->Self{
+        Self::new(NodeKind::Text(data.to_string()))
+    }
+
+    pub fn kind(&self)->NodeKind{
+        self.0.borrow().kind.clone()
+    }
+    /// Tag name, `None` for non-element nodes
+    pub fn tag(&self)->Option<String>{
+        match &self.0.borrow().kind{
+            NodeKind::Element{tag, ..}=>Some(tag.clone()),
+            _=>None
+        }
+    }
+    pub fn attribute(&self, name:&str)->Option<String>{
+        match &self.0.borrow().kind{
+            NodeKind::Element{attributes, ..}=>attributes.get(name).cloned(),
+            _=>None
+        }
+    }
+    pub fn attributes(&self)->BTreeMap<String, String>{
+        match &self.0.borrow().kind{
+            NodeKind::Element{attributes, ..}=>attributes.clone(),
+            _=>BTreeMap::new()
+        }
+    }
+    /// Concatenated text of this node and all of its descendants
+    pub fn text_content(&self)->String{
+        let data = self.0.borrow();
+        match &data.kind{
+            NodeKind::Text(text)=>text.clone(),
+            NodeKind::Html(html)=>html.clone(),
+            NodeKind::Element{..} | NodeKind::Fragment=>{
+                data.children.iter().map(|child| child.text_content()).collect()
+            }
+        }
+    }
+    /// All child nodes, including text
+    pub fn child_nodes(&self)->Vec<Node>{
+        self.0.borrow().children.clone()
+    }
+    /// List key set with `key={...}`
+    pub fn key(&self)->Option<String>{
+        self.0.borrow().key.clone()
+    }
+    /// `value` property, initially the `value` attribute
+    pub fn value(&self)->String{
+        let value = self.0.borrow().value.clone();
+        value.or_else(|| self.attribute("value")).unwrap_or_default()
+    }
+    /// Sets the `value` property, like a user typing into the control
+    pub fn set_value(&self, value:&str){
+        self.0.borrow_mut().value = Some(value.to_string());
+    }
+    /// `checked` property, initially the presence of the `checked` attribute
+    pub fn checked(&self)->bool{
+        let checked = self.0.borrow().checked;
+        checked.unwrap_or_else(|| self.attribute("checked").is_some())
+    }
+    pub fn set_checked(&self, checked:bool){
+        self.0.borrow_mut().checked = Some(checked);
+    }
+    pub fn is_element(&self)->bool{
+        matches!(self.0.borrow().kind, NodeKind::Element{..})
+    }
+
+    pub fn parent(&self)->Option<Node>{
+        self.0.borrow().parent.upgrade().map(Node)
+    }
+    pub fn next_sibling(&self)->Option<Node>{
+        let parent = self.parent()?;
+        let siblings = parent.0.borrow();
+        let index = siblings.children.iter().position(|child| child == self)?;
+        siblings.children.get(index+1).cloned()
+    }
+
+    fn set_attribute(&self, name:&str, value:&str){
+        if let NodeKind::Element{attributes, ..} = &mut self.0.borrow_mut().kind{
+            attributes.insert(name.to_string(), value.to_string());
+        }
+    }
+    fn remove_attribute(&self, name:&str){
+        if let NodeKind::Element{attributes, ..} = &mut self.0.borrow_mut().kind{
+            attributes.remove(name);
+        }
+    }
+    fn set_text(&self, data:&str){
+        if let NodeKind::Text(text) = &mut self.0.borrow_mut().kind{
+            *text = data.to_string();
+        }
+    }
+    /// Removes the node from its current parent, if any
+    fn detach(&self){
+        if let Some(parent) = self.parent(){
+            parent.0.borrow_mut().children.retain(|child| child != self);
+            self.0.borrow_mut().parent = Weak::new();
+        }
+    }
+    fn insert_before(&self, child:&Node, reference:Option<&Node>){
+        if matches!(child.0.borrow().kind, NodeKind::Fragment){
+            let nodes = std::mem::take(&mut child.0.borrow_mut().children);
+            for node in nodes{
+                node.0.borrow_mut().parent = Weak::new();
+                self.insert_before(&node, reference);
+            }
+            return;
+        }
+        child.detach();
+        child.0.borrow_mut().parent = Rc::downgrade(&self.0);
+        let mut data = self.0.borrow_mut();
+        let index = reference
+            .and_then(|reference| data.children.iter().position(|c| c == reference))
+            .unwrap_or(data.children.len());
+        data.children.insert(index, child.clone());
+    }
+    fn append_child(&self, child:&Node){
+        self.insert_before(child, None);
+    }
+
+    /// Names of the events this node currently listens to
+    pub fn listeners(&self)->Vec<String>{
+        self.0.borrow().listeners.iter()
+            .map(|(_, event, _)| event.clone())
+            .collect()
+    }
+    /// Invokes the handlers registered for `event` with an
+    /// [`Event::Memory`] targeting this node
+    pub fn dispatch(&self, event:&str){
+        let handlers:Vec<_> = self.0.borrow().listeners.iter()
+            .filter(|(_, name, _)| name == event)
+            .map(|(_, _, handler)| handler.clone())
+            .collect();
+        for handler in handlers{
+            (handler.borrow_mut())(Event::Memory(MemoryEvent{
+                event_type:event.to_string(),
+                target:self.clone()
+            }));
+        }
+    }
+
+    /// Serializes the node the same way `outerHTML` would
+    pub fn html(&self)->String{
+        let mut buf = String::new();
+        self.write_html(&mut buf).unwrap();
+        buf
+    }
+    fn write_html<W:fmt::Write>(&self, w:&mut W)->fmt::Result{
+        let data = self.0.borrow();
+        match &data.kind{
+            NodeKind::Text(text)=>{
+                write!(w, "{}", escape_html(text.as_str()))?;
+            }
+            NodeKind::Html(html)=>{
+                w.write_str(html)?;
+            }
+            NodeKind::Fragment=>{
+                for child in &data.children{
+                    child.write_html(w)?;
+                }
+            }
+            NodeKind::Element{tag, attributes}=>{
+                write!(w, "<{}", tag)?;
+                for (name, value) in attributes{
+                    write!(w, " {}=\"{}\"", name, escape_attr(value.as_str()))?;
+                }
+                write!(w, ">")?;
+                if is_void_element(tag){
+                    return Ok(());
+                }
+                for child in &data.children{
+                    child.write_html(w)?;
+                }
+                write!(w, "</{}>", tag)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl PartialEq for Node{
+    fn eq(&self, other:&Self)->bool{
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Node{}
+
+/// Hashes the identity of the node, like `==` compares it
+impl Hash for Node{
+    fn hash<H:Hasher>(&self, state:&mut H){
+        Rc::as_ptr(&self.0).hash(state);
+    }
+}
+
+impl fmt::Debug for Node{
+    fn fmt(&self, f:&mut fmt::Formatter<'_>)->fmt::Result{
+        write!(f, "Node({})", self.html())
+    }
+}
+
+/// Handler attached to a memory [`Node`], detached when dropped
+pub struct MemoryListener{
+    node:Weak<RefCell<NodeData>>,
+    id:u64
+}
+
+impl Drop for MemoryListener{
+    fn drop(&mut self){
+        if let Some(node) = self.node.upgrade(){
+            node.borrow_mut().listeners.retain(|(id, _, _)| *id != self.id);
+        }
+    }
+}
+
+impl HookCast<MemoryBackend> for Node{
+    fn cast(_backend:&MemoryBackend, element:&Node)->Option<Self>{
+        Some(element.clone())
+    }
+}
+
+macro_rules! typed_elements {
+    ($($name:ident $tag:literal),*) => {
+        $(
+            #[doc = concat!("`<", $tag, ">` element, a hook of this type checks the tag")]
+            #[derive(Debug, Clone, PartialEq)]
+            pub struct $name(pub Node);
+
+            impl std::ops::Deref for $name{
+                type Target = Node;
+                fn deref(&self)->&Node{
+                    &self.0
+                }
+            }
+
+            impl HookCast<MemoryBackend> for $name{
+                fn cast(_backend:&MemoryBackend, element:&Node)->Option<Self>{
+                    (element.tag().as_deref() == Some($tag)).then(|| $name(element.clone()))
+                }
+            }
+        )*
+    };
+}
+
+typed_elements!(Input "input", TextArea "textarea", Select "select", Button "button", Form "form");
+
+/// Pure Rust document, used to build and inspect DOM trees outside of a browser
+#[derive(Debug, Clone, Default)]
+pub struct MemoryBackend;
+
+impl DomBackend for MemoryBackend{
+    type Node = Node;
+    type Element = Node;
+    type Listener = MemoryListener;
+
+    fn create_element(&self, tag:&str)->ElementResult<Node>{
+        // same names `document.createElement` rejects
+        let mut chars = tag.chars();
+        let valid = chars.next().is_some_and(|first| first.is_ascii_alphabetic())
+            && chars.all(|c| !c.is_whitespace() && !matches!(c, '/' | '>' | '<' | '"' | '\'' | '=' | '\0'));
+        if !valid{
+            return Err(Error::InvalidTagName(tag.to_string()));
+        }
+        Ok(Node::element(tag))
+    }
+    fn create_text(&self, data:&str)->ElementResult<Node>{
+        Ok(Node::text(data))
+    }
+    fn set_attribute(&self, element:&Node, name:&str, value:&str)->ElementResult<()>{
+        element.set_attribute(name, value);
+        Ok(())
+    }
+    fn append_child(&self, parent:&Node, child:&Node)->ElementResult<()>{
+        parent.append_child(child);
+        Ok(())
+    }
+    fn append_html(&self, parent:&Node, html:&str)->ElementResult<()>{
+        parent.append_child(&Node::new(NodeKind::Html(html.to_string())));
+        Ok(())
+    }
+    fn fragment(&self, nodes:&[Node])->ElementResult<Node>{
+        let fragment = Node::new(NodeKind::Fragment);
+        for node in nodes{
+            fragment.append_child(node);
+        }
+        Ok(fragment)
+    }
+    fn children(&self, parent:&Node)->Vec<Node>{
+        parent.child_nodes()
+            .into_iter()
+            .filter(|child| child.is_element())
+            .collect()
+    }
+    fn element_node(&self, element:&Node)->Node{
+        element.clone()
+    }
+    fn node_element(&self, node:&Node)->Option<Node>{
+        node.is_element().then(|| node.clone())
+    }
+    fn tag_name(&self, element:&Node)->String{
+        element.tag().unwrap_or_default()
+    }
+    fn attributes(&self, element:&Node)->Vec<(String, String)>{
+        element.attributes().into_iter().collect()
+    }
+    fn remove_attribute(&self, element:&Node, name:&str)->ElementResult<()>{
+        element.remove_attribute(name);
+        Ok(())
+    }
+    fn child_nodes(&self, parent:&Node)->Vec<Node>{
+        parent.child_nodes()
+    }
+    fn text(&self, node:&Node)->Option<String>{
+        match node.kind(){
+            NodeKind::Text(text)=>Some(text),
+            _=>None
+        }
+    }
+    fn set_text(&self, node:&Node, data:&str)->ElementResult<()>{
+        node.set_text(data);
+        Ok(())
+    }
+    fn insert_before(&self, parent:&Node, child:&Node, reference:Option<&Node>)->ElementResult<()>{
+        parent.insert_before(child, reference);
+        Ok(())
+    }
+    fn remove_child(&self, _parent:&Node, child:&Node)->ElementResult<()>{
+        child.detach();
+        Ok(())
+    }
+    /// `None` in a fragment, like `parentElement`
+    fn parent(&self, node:&Node)->Option<Node>{
+        node.parent().filter(Node::is_element)
+    }
+    fn next_sibling(&self, node:&Node)->Option<Node>{
+        node.next_sibling()
+    }
+    fn set_key(&self, element:&Node, key:&str)->ElementResult<()>{
+        element.0.borrow_mut().key = Some(key.to_string());
+        Ok(())
+    }
+    fn key(&self, element:&Node)->Option<String>{
+        element.key()
+    }
+    /// There is no document in memory, nothing matches
+    fn query_selector(&self, _selector:&str)->ElementResult<Option<Node>>{
+        Ok(None)
+    }
+    fn value(&self, element:&Node)->String{
+        element.value()
+    }
+    fn set_value(&self, element:&Node, value:&str)->ElementResult<()>{
+        element.set_value(value);
+        Ok(())
+    }
+    fn checked(&self, element:&Node)->bool{
+        element.checked()
+    }
+    fn set_checked(&self, element:&Node, checked:bool)->ElementResult<()>{
+        element.set_checked(checked);
+        Ok(())
+    }
+    fn add_event_listener(&self, element:&Node, event:&str, handler:EventHandler)->ElementResult<MemoryListener>{
+        let id = LISTENER_ID.fetch_add(1, Ordering::Relaxed);
+        element.0.borrow_mut().listeners.push((id, event.to_string(), Rc::new(RefCell::new(handler))));
+        Ok(MemoryListener{
+            node:Rc::downgrade(&element.0),
+            id
+        })
+    }
+}
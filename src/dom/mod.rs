@@ -0,0 +1,211 @@
+//! DOM construction used by [`Render::render_node`](crate::Render::render_node).
+//!
+//! Rendering talks to the document through a [`DomBackend`], so the same
+//! tree can be mounted into the browser ([`WebBackend`]) or built in memory
+//! ([`MemoryBackend`]) e.g. to inspect the produced nodes in native tests.
+pub mod web;
+pub mod memory;
+pub mod patch;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::utils::{ElementResult, Error};
+use crate::signal::Reactive;
+use crate::bind::FormBinding;
+pub use web::WebBackend;
+pub use memory::MemoryBackend;
+
+/// Event passed to `on:event` handlers
+#[derive(Debug, Clone)]
+pub enum Event{
+    /// Browser event delivered through the [`WebBackend`]
+    Web(web_sys::Event),
+    /// Event dispatched on a [`memory::Node`]
+    Memory(MemoryEvent)
+}
+
+impl Event{
+    /// Name of the event, e.g. `"click"`
+    pub fn event_type(&self)->String{
+        match self{
+            Event::Web(event)=>event.type_(),
+            Event::Memory(event)=>event.event_type.clone()
+        }
+    }
+    /// The browser event, `None` in the memory backend
+    pub fn as_web(&self)->Option<&web_sys::Event>{
+        match self{
+            Event::Web(event)=>Some(event),
+            Event::Memory(_)=>None
+        }
+    }
+    /// Calls `preventDefault` on browser events
+    pub fn prevent_default(&self){
+        if let Event::Web(event) = self{
+            event.prevent_default();
+        }
+    }
+}
+
+/// Event created by [`memory::Node::dispatch`]
+#[derive(Debug, Clone)]
+pub struct MemoryEvent{
+    pub event_type:String,
+    /// Node the event was dispatched on
+    pub target:memory::Node
+}
+
+pub type EventHandler = Box<dyn FnMut(Event)>;
+
+/// Event handler declared on an element with `on:event={closure}`
+pub struct Listener<'a>{
+    pub event:&'a str,
+    pub handler:EventHandler
+}
+
+impl<'a> Listener<'a>{
+    pub fn new<F:FnMut(Event)+'static>(event:&'a str, handler:F)->Self{
+        Self{
+            event,
+            handler:Box::new(handler)
+        }
+    }
+}
+
+impl fmt::Debug for Listener<'_>{
+    fn fmt(&self, f:&mut fmt::Formatter<'_>)->fmt::Result{
+        f.debug_struct("Listener").field("event", &self.event).finish()
+    }
+}
+
+/// Typed view of an element of backend `B`, used by [`Html::hook`](crate::Html::hook).
+///
+/// Every `JsCast` type is one for the [`WebBackend`], the [`memory`] module
+/// has tag checked handles for the [`MemoryBackend`].
+pub trait HookCast<B:DomBackend>: Sized{
+    /// `None` when `element` is not of this type
+    fn cast(backend:&B, element:&B::Element)->Option<Self>;
+}
+
+pub trait DomBackend: Clone+'static {
+    /// Any node that can be placed into an element (element, text)
+    type Node: Clone+PartialEq+'static;
+    type Element: Clone+'static;
+    /// Keeps an attached event handler alive, removes it when dropped
+    type Listener;
+
+    fn create_element(&self, tag:&str)->ElementResult<Self::Element>;
+    fn create_text(&self, data:&str)->ElementResult<Self::Node>;
+    fn set_attribute(&self, element:&Self::Element, name:&str, value:&str)->ElementResult<()>;
+    fn append_child(&self, parent:&Self::Element, child:&Self::Node)->ElementResult<()>;
+    /// Appends trusted markup to `parent` without escaping it
+    fn append_html(&self, parent:&Self::Element, html:&str)->ElementResult<()>;
+    /// Moves `nodes` out of their parent into a new document fragment,
+    /// inserting the returned fragment inserts all of them in one call
+    fn fragment(&self, nodes:&[Self::Node])->ElementResult<Self::Node>;
+    /// Element children of `parent`
+    fn children(&self, parent:&Self::Element)->Vec<Self::Element>;
+    fn element_node(&self, element:&Self::Element)->Self::Node;
+    /// Downcasts `node`, `None` if it is not an element
+    fn node_element(&self, node:&Self::Node)->Option<Self::Element>;
+
+    /// Lowercase tag name of `element`
+    fn tag_name(&self, element:&Self::Element)->String;
+    fn attributes(&self, element:&Self::Element)->Vec<(String, String)>;
+    fn remove_attribute(&self, element:&Self::Element, name:&str)->ElementResult<()>;
+    /// All child nodes of `parent`, including text
+    fn child_nodes(&self, parent:&Self::Element)->Vec<Self::Node>;
+    /// Content of a text node, `None` for other node types
+    fn text(&self, node:&Self::Node)->Option<String>;
+    fn set_text(&self, node:&Self::Node, data:&str)->ElementResult<()>;
+    /// Inserts `child` before `reference`, or at the end when `reference` is `None`
+    fn insert_before(&self, parent:&Self::Element, child:&Self::Node, reference:Option<&Self::Node>)->ElementResult<()>;
+    fn remove_child(&self, parent:&Self::Element, child:&Self::Node)->ElementResult<()>;
+    fn parent(&self, node:&Self::Node)->Option<Self::Element>;
+    fn next_sibling(&self, node:&Self::Node)->Option<Self::Node>;
+    /// Associates a list `key` with `element`, kept outside of its attributes
+    fn set_key(&self, element:&Self::Element, key:&str)->ElementResult<()>;
+    fn key(&self, element:&Self::Element)->Option<String>;
+    /// First element of the document matching the CSS `selector`
+    fn query_selector(&self, selector:&str)->ElementResult<Option<Self::Element>>;
+    /// Current `value` property of a form control
+    fn value(&self, element:&Self::Element)->String;
+    fn set_value(&self, element:&Self::Element, value:&str)->ElementResult<()>;
+    /// Current `checked` property of a form control
+    fn checked(&self, element:&Self::Element)->bool;
+    fn set_checked(&self, element:&Self::Element, checked:bool)->ElementResult<()>;
+    fn add_event_listener(&self, element:&Self::Element, event:&str, handler:EventHandler)->ElementResult<Self::Listener>;
+
+    fn append_text(&self, parent:&Self::Element, data:&str)->ElementResult<()>{
+        let text = self.create_text(data)?;
+        self.append_child(parent, &text)
+    }
+}
+
+/// Part of the DOM kept up to date with a [`Reactive`] value
+pub enum Binding<B:DomBackend>{
+    Text(B::Node),
+    Attribute(B::Element, String)
+}
+
+static SCOPE:AtomicU64 = AtomicU64::new(1);
+
+/// Identifier of a new [`Html`](crate::Html) instance, appended to its `data-ref` values
+pub fn next_scope()->u64{
+    SCOPE.fetch_add(1, Ordering::Relaxed)
+}
+
+/// State threaded through [`Render::render_node`](crate::Render::render_node)
+pub struct Context<B:DomBackend>{
+    pub backend:B,
+    /// Keeps the `data-ref` values of separate mounts apart
+    pub scope:u64,
+    pub hooks:BTreeMap<String, B::Element>,
+    /// Handlers waiting to be attached by the [`Html`](crate::Html) that owns the view
+    pub listeners:Vec<(B::Element, String, EventHandler)>,
+    /// Nodes bound to signals, subscribed by the owning [`Html`](crate::Html)
+    pub bindings:Vec<(Binding<B>, Box<dyn Reactive>)>,
+    /// Form controls bound to Rust state, connected by the owning [`Html`](crate::Html)
+    pub inputs:Vec<(B::Element, FormBinding)>
+}
+
+impl<B:DomBackend> Context<B>{
+    pub fn new(backend:B)->Self{
+        Self::with_scope(backend, next_scope())
+    }
+
+    /// Context re-rendering the view of an existing `Html` with the given scope
+    pub fn with_scope(backend:B, scope:u64)->Self{
+        Self{
+            backend,
+            scope,
+            hooks:BTreeMap::new(),
+            listeners:vec![],
+            bindings:vec![],
+            inputs:vec![]
+        }
+    }
+
+    pub fn add_listener(&mut self, element:&B::Element, listener:Listener){
+        self.listeners.push((element.clone(), listener.event.to_string(), listener.handler));
+    }
+
+    pub fn bind(&mut self, binding:Binding<B>, value:Box<dyn Reactive>){
+        self.bindings.push((binding, value));
+    }
+
+    pub fn bind_input(&mut self, element:&B::Element, binding:FormBinding){
+        self.inputs.push((element.clone(), binding));
+    }
+
+    /// Marks `element` with a scoped `data-ref` and registers it as hook `name`
+    pub fn set_ref(&mut self, element:&B::Element, name:&str, value:&str)->ElementResult<()>{
+        if self.hooks.contains_key(name){
+            return Err(Error::DuplicateRef(name.to_string()));
+        }
+        self.backend.set_attribute(element, "data-ref", &format!("{}-{}", value, self.scope))?;
+        self.hooks.insert(name.to_string(), element.clone());
+        Ok(())
+    }
+}
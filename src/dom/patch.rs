@@ -0,0 +1,266 @@
+//! Reconciles mounted DOM nodes with a freshly rendered in-memory tree
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use crate::utils::ElementResult;
+use super::DomBackend;
+use super::memory::{Node as VNode, NodeKind};
+
+pub struct Patcher<'a, B:DomBackend>{
+    backend:&'a B,
+    /// DOM nodes that now represent the virtual nodes, by node identity
+    mapped:HashMap<VNode, B::Node>
+}
+
+impl<'a, B:DomBackend> Patcher<'a, B>{
+    pub fn new(backend:&'a B)->Self{
+        Self{
+            backend,
+            mapped:HashMap::new()
+        }
+    }
+
+    /// DOM element representing the virtual element `vnode` after patching
+    pub fn mapped(&self, vnode:&VNode)->Option<B::Element>{
+        self.mapped_node(vnode)
+            .and_then(|node| self.backend.node_element(node))
+    }
+
+    /// DOM node (element or text) representing `vnode` after patching
+    pub fn mapped_node(&self, vnode:&VNode)->Option<&B::Node>{
+        self.mapped.get(vnode)
+    }
+
+    /// Updates `node` in place when it can represent `vnode`, otherwise
+    /// replaces it, returns the nodes now standing for `vnode`
+    pub fn patch(&mut self, node:&B::Node, vnode:&VNode)->ElementResult<Vec<B::Node>>{
+        if self.is_same(node, vnode){
+            self.patch_node(node, vnode)?;
+            return Ok(vec![node.clone()]);
+        }
+        let replacement = self.create_nodes(vnode)?;
+        if let Some(parent) = self.backend.parent(node){
+            for created in &replacement{
+                self.backend.insert_before(&parent, created, Some(node))?;
+            }
+            self.backend.remove_child(&parent, node)?;
+        }
+        Ok(replacement)
+    }
+
+    fn is_same(&self, node:&B::Node, vnode:&VNode)->bool{
+        match vnode.kind(){
+            NodeKind::Element{tag, ..}=>{
+                match self.backend.node_element(node){
+                    Some(element)=>self.backend.tag_name(&element) == tag,
+                    None=>false
+                }
+            }
+            NodeKind::Text(_)=>self.backend.text(node).is_some(),
+            NodeKind::Html(_) | NodeKind::Fragment=>false
+        }
+    }
+
+    fn patch_node(&mut self, node:&B::Node, vnode:&VNode)->ElementResult<()>{
+        match vnode.kind(){
+            NodeKind::Element{attributes, ..}=>{
+                let element = self.backend.node_element(node).unwrap();
+                let current:BTreeMap<String, String> = self.backend.attributes(&element)
+                    .into_iter()
+                    .collect();
+                for name in current.keys(){
+                    if !attributes.contains_key(name){
+                        self.backend.remove_attribute(&element, name)?;
+                    }
+                }
+                for (name, value) in &attributes{
+                    if current.get(name) != Some(value){
+                        self.backend.set_attribute(&element, name, value)?;
+                    }
+                }
+                self.patch_children(&element, vnode)?;
+                self.mapped.insert(vnode.clone(), node.clone());
+            }
+            NodeKind::Text(text)=>{
+                if self.backend.text(node).as_ref() != Some(&text){
+                    self.backend.set_text(node, &text)?;
+                }
+                self.mapped.insert(vnode.clone(), node.clone());
+            }
+            NodeKind::Html(_) | NodeKind::Fragment=>{}
+        }
+        Ok(())
+    }
+
+    fn patch_children(&mut self, parent:&B::Element, vparent:&VNode)->ElementResult<()>{
+        let old = self.backend.child_nodes(parent);
+        let new = vparent.child_nodes();
+        // raw markup does not map 1:1 to DOM nodes, such lists are rebuilt
+        let has_html = new.iter().any(|vnode| matches!(vnode.kind(), NodeKind::Html(_)));
+        if has_html{
+            for node in &old{
+                self.backend.remove_child(parent, node)?;
+            }
+            for vnode in &new{
+                self.append(parent, vnode)?;
+            }
+            return Ok(());
+        }
+        if new.iter().any(|vnode| vnode.key().is_some()){
+            return self.patch_keyed_children(parent, old, &new);
+        }
+
+        for (index, vnode) in new.iter().enumerate(){
+            match old.get(index){
+                Some(node) if self.is_same(node, vnode)=>{
+                    self.patch_node(node, vnode)?;
+                }
+                Some(node)=>{
+                    let created = self.create(vnode)?;
+                    self.backend.insert_before(parent, &created, Some(node))?;
+                    self.backend.remove_child(parent, node)?;
+                }
+                None=>{
+                    self.append(parent, vnode)?;
+                }
+            }
+        }
+        for node in old.iter().skip(new.len()){
+            self.backend.remove_child(parent, node)?;
+        }
+        Ok(())
+    }
+
+    /// Matches children by their `key` so that reordered items are moved
+    /// instead of re-created, unkeyed children are matched in order
+    fn patch_keyed_children(&mut self, parent:&B::Element, old:Vec<B::Node>, new:&[VNode])->ElementResult<()>{
+        let nodes = self.match_keyed(old, new)?;
+        self.place(parent, &nodes, None)
+    }
+
+    /// Keyed reconciliation of the top level nodes of a view, `parent` is
+    /// the element they are mounted in, if any
+    pub fn patch_keyed_roots(&mut self, parent:Option<&B::Element>, old:&[B::Node], new:&[VNode])->ElementResult<Vec<B::Node>>{
+        // the first node after the view that does not belong to it
+        let mut next = old.last().and_then(|node| self.backend.next_sibling(node));
+        while let Some(node) = next.as_ref().filter(|node| old.contains(node)){
+            next = self.backend.next_sibling(node);
+        }
+        let nodes = self.match_keyed(old.to_vec(), new)?;
+        if let Some(parent) = parent{
+            self.place(parent, &nodes, next)?;
+        }
+        Ok(nodes)
+    }
+
+    /// Pairs every vnode with the old node of the same key (unkeyed ones in
+    /// order), patching or re-creating it, the old nodes left over are removed
+    fn match_keyed(&mut self, old:Vec<B::Node>, new:&[VNode])->ElementResult<Vec<B::Node>>{
+        // duplicate keys are matched in order like unkeyed nodes
+        let mut keyed:BTreeMap<String, VecDeque<B::Node>> = BTreeMap::new();
+        let mut unkeyed = VecDeque::new();
+        for node in old{
+            let key = self.backend.node_element(&node)
+                .and_then(|element| self.backend.key(&element));
+            match key{
+                Some(key)=>keyed.entry(key).or_default().push_back(node),
+                None=>unkeyed.push_back(node)
+            }
+        }
+
+        let mut nodes = Vec::with_capacity(new.len());
+        for vnode in new{
+            let existing = match vnode.key(){
+                Some(key)=>keyed.get_mut(&key).and_then(VecDeque::pop_front),
+                None=>unkeyed.pop_front()
+            };
+            let node = match existing{
+                Some(node) if self.is_same(&node, vnode)=>{
+                    self.patch_node(&node, vnode)?;
+                    node
+                }
+                Some(node)=>{
+                    self.detach(&node)?;
+                    self.create(vnode)?
+                }
+                None=>self.create(vnode)?
+            };
+            nodes.push(node);
+        }
+        for node in keyed.values().flatten().chain(unkeyed.iter()){
+            self.detach(node)?;
+        }
+        Ok(nodes)
+    }
+
+    /// Puts `nodes` in order into `parent` before `next`, back to front and
+    /// only touching the ones out of position
+    fn place(&self, parent:&B::Element, nodes:&[B::Node], mut next:Option<B::Node>)->ElementResult<()>{
+        let parent_node = self.backend.element_node(parent);
+        for node in nodes.iter().rev(){
+            let in_place = self.backend.parent(node)
+                .is_some_and(|current| self.backend.element_node(&current) == parent_node)
+                && self.backend.next_sibling(node) == next;
+            if !in_place{
+                self.backend.insert_before(parent, node, next.as_ref())?;
+            }
+            next = Some(node.clone());
+        }
+        Ok(())
+    }
+
+    fn detach(&self, node:&B::Node)->ElementResult<()>{
+        match self.backend.parent(node){
+            Some(parent)=>self.backend.remove_child(&parent, node),
+            None=>Ok(())
+        }
+    }
+
+    fn append(&mut self, parent:&B::Element, vnode:&VNode)->ElementResult<()>{
+        if let NodeKind::Html(html) = vnode.kind(){
+            return self.backend.append_html(parent, &html);
+        }
+        let node = self.create(vnode)?;
+        self.backend.append_child(parent, &node)
+    }
+
+    /// Creates the DOM nodes for `vnode`, raw markup may result in several
+    pub fn create_nodes(&mut self, vnode:&VNode)->ElementResult<Vec<B::Node>>{
+        if let NodeKind::Html(html) = vnode.kind(){
+            let container = self.backend.create_element("div")?;
+            self.backend.append_html(&container, &html)?;
+            let nodes = self.backend.child_nodes(&container);
+            self.backend.fragment(&nodes)?;
+            return Ok(nodes);
+        }
+        Ok(vec![self.create(vnode)?])
+    }
+
+    /// Creates a DOM node for `vnode` (element or text)
+    pub fn create(&mut self, vnode:&VNode)->ElementResult<B::Node>{
+        match vnode.kind(){
+            NodeKind::Text(text)=>{
+                let node = self.backend.create_text(&text)?;
+                self.mapped.insert(vnode.clone(), node.clone());
+                Ok(node)
+            }
+            _=>{
+                let element = self.create_element(vnode)?;
+                Ok(self.backend.element_node(&element))
+            }
+        }
+    }
+
+    pub fn create_element(&mut self, vnode:&VNode)->ElementResult<B::Element>{
+        let element = self.backend.create_element(&vnode.tag().unwrap_or_default())?;
+        for (name, value) in vnode.attributes(){
+            self.backend.set_attribute(&element, &name, &value)?;
+        }
+        if let Some(key) = vnode.key(){
+            self.backend.set_key(&element, &key)?;
+        }
+        for child in vnode.child_nodes(){
+            self.append(&element, &child)?;
+        }
+        self.mapped.insert(vnode.clone(), self.backend.element_node(&element));
+        Ok(element)
+    }
+}
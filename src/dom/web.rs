@@ -0,0 +1,193 @@
+use web_sys::{Document, Element, Node, HtmlTemplateElement};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen::closure::Closure;
+use js_sys::Reflect;
+use crate::utils::{ElementResult, Error, document};
+use super::{DomBackend, HookCast, Event, EventHandler};
+
+/// Browser DOM backed by web-sys
+#[derive(Clone)]
+pub struct WebBackend{
+    document:Document
+}
+
+impl WebBackend{
+    /// Backend for the document of the global `window`, there is no `Default`
+    /// as workers and server side rendering have none
+    pub fn new()->ElementResult<Self>{
+        Ok(Self::with_document(document()?))
+    }
+    pub fn with_document(document:Document)->Self{
+        Self{
+            document
+        }
+    }
+    pub fn document(&self)->&Document{
+        &self.document
+    }
+}
+
+impl<T:JsCast> HookCast<WebBackend> for T{
+    fn cast(_backend:&WebBackend, element:&Element)->Option<Self>{
+        element.clone().dyn_into::<T>().ok()
+    }
+}
+
+/// Property holding the list key of an element
+const KEY_PROPERTY:&str = "__flowHtmlKey";
+
+/// Closure registered with `addEventListener`, removed again on drop
+pub struct WebListener{
+    element:Element,
+    event:String,
+    closure:Closure<dyn FnMut(web_sys::Event)>
+}
+
+impl Drop for WebListener{
+    fn drop(&mut self){
+        let _ = self.element.remove_event_listener_with_callback(
+            &self.event,
+            self.closure.as_ref().unchecked_ref()
+        );
+    }
+}
+
+impl DomBackend for WebBackend{
+    type Node = Node;
+    type Element = Element;
+    type Listener = WebListener;
+
+    fn create_element(&self, tag:&str)->ElementResult<Element>{
+        self.document.create_element(tag)
+            .map_err(|_| Error::InvalidTagName(tag.to_string()))
+    }
+    fn create_text(&self, data:&str)->ElementResult<Node>{
+        Ok(self.document.create_text_node(data).into())
+    }
+    fn set_attribute(&self, element:&Element, name:&str, value:&str)->ElementResult<()>{
+        Ok(element.set_attribute(name, value)?)
+    }
+    fn append_child(&self, parent:&Element, child:&Node)->ElementResult<()>{
+        parent.append_child(child)?;
+        Ok(())
+    }
+    fn append_html(&self, parent:&Element, html:&str)->ElementResult<()>{
+        let template = self.document.create_element("template")?;
+        template.set_inner_html(html);
+        let content = template.unchecked_into::<HtmlTemplateElement>().content();
+        parent.append_child(&content)?;
+        Ok(())
+    }
+    fn fragment(&self, nodes:&[Node])->ElementResult<Node>{
+        let fragment = self.document.create_document_fragment();
+        for node in nodes{
+            fragment.append_child(node)?;
+        }
+        Ok(fragment.into())
+    }
+    fn children(&self, parent:&Element)->Vec<Element>{
+        let children = parent.children();
+        let mut list = vec![];
+        for index in 0..children.length(){
+            if let Some(child) = children.get_with_index(index){
+                list.push(child);
+            }
+        }
+        list
+    }
+    fn element_node(&self, element:&Element)->Node{
+        element.clone().into()
+    }
+    fn node_element(&self, node:&Node)->Option<Element>{
+        node.dyn_ref::<Element>().cloned()
+    }
+    fn tag_name(&self, element:&Element)->String{
+        element.local_name()
+    }
+    fn attributes(&self, element:&Element)->Vec<(String, String)>{
+        let attributes = element.attributes();
+        let mut list = vec![];
+        for index in 0..attributes.length(){
+            if let Some(attr) = attributes.item(index){
+                list.push((attr.name(), attr.value()));
+            }
+        }
+        list
+    }
+    fn remove_attribute(&self, element:&Element, name:&str)->ElementResult<()>{
+        Ok(element.remove_attribute(name)?)
+    }
+    fn child_nodes(&self, parent:&Element)->Vec<Node>{
+        let nodes = parent.child_nodes();
+        let mut list = vec![];
+        for index in 0..nodes.length(){
+            if let Some(node) = nodes.item(index){
+                list.push(node);
+            }
+        }
+        list
+    }
+    fn text(&self, node:&Node)->Option<String>{
+        if node.node_type() == Node::TEXT_NODE{
+            node.node_value()
+        }else{
+            None
+        }
+    }
+    fn set_text(&self, node:&Node, data:&str)->ElementResult<()>{
+        node.set_node_value(Some(data));
+        Ok(())
+    }
+    fn insert_before(&self, parent:&Element, child:&Node, reference:Option<&Node>)->ElementResult<()>{
+        parent.insert_before(child, reference)?;
+        Ok(())
+    }
+    fn remove_child(&self, parent:&Element, child:&Node)->ElementResult<()>{
+        parent.remove_child(child)?;
+        Ok(())
+    }
+    fn parent(&self, node:&Node)->Option<Element>{
+        node.parent_element()
+    }
+    fn next_sibling(&self, node:&Node)->Option<Node>{
+        node.next_sibling()
+    }
+    fn set_key(&self, element:&Element, key:&str)->ElementResult<()>{
+        Reflect::set(element, &JsValue::from_str(KEY_PROPERTY), &JsValue::from_str(key))?;
+        Ok(())
+    }
+    fn key(&self, element:&Element)->Option<String>{
+        Reflect::get(element, &JsValue::from_str(KEY_PROPERTY)).ok()?.as_string()
+    }
+    fn query_selector(&self, selector:&str)->ElementResult<Option<Element>>{
+        Ok(self.document.query_selector(selector)?)
+    }
+    fn value(&self, element:&Element)->String{
+        Reflect::get(element, &JsValue::from_str("value")).ok()
+            .and_then(|value| value.as_string())
+            .unwrap_or_default()
+    }
+    fn set_value(&self, element:&Element, value:&str)->ElementResult<()>{
+        Reflect::set(element, &JsValue::from_str("value"), &JsValue::from_str(value))?;
+        Ok(())
+    }
+    fn checked(&self, element:&Element)->bool{
+        Reflect::get(element, &JsValue::from_str("checked")).ok()
+            .and_then(|checked| checked.as_bool())
+            .unwrap_or(false)
+    }
+    fn set_checked(&self, element:&Element, checked:bool)->ElementResult<()>{
+        Reflect::set(element, &JsValue::from_str("checked"), &JsValue::from_bool(checked))?;
+        Ok(())
+    }
+    fn add_event_listener(&self, element:&Element, event:&str, handler:EventHandler)->ElementResult<WebListener>{
+        let mut handler = handler;
+        let closure = Closure::wrap(Box::new(move |event:web_sys::Event| handler(Event::Web(event))) as Box<dyn FnMut(web_sys::Event)>);
+        element.add_event_listener_with_callback(event, closure.as_ref().unchecked_ref())?;
+        Ok(WebListener{
+            element:element.clone(),
+            event:event.to_string(),
+            closure
+        })
+    }
+}
@@ -0,0 +1,81 @@
+use std::fmt;
+#[cfg(feature = "dom")]
+use wasm_bindgen::{JsCast, JsValue};
+#[cfg(feature = "dom")]
+use crate::interface::HookError;
+
+/// Errors of rendering and DOM operations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// No global `window`, e.g. in a worker or outside the browser
+    NoWindow,
+    /// The window has no `document`
+    NoDocument,
+    /// Exception thrown by a DOM call
+    Dom(String),
+    /// Writing the html output failed
+    Format,
+    /// Two elements were rendered with the same `@name`
+    DuplicateRef(String),
+    InvalidTagName(String),
+    /// Mount target given by a selector that matches nothing
+    NoMatch(String),
+    /// Mount reference node without a parent
+    NotAttached,
+    #[cfg(feature = "dom")]
+    Hook(HookError)
+}
+
+pub type ElementResult<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NoWindow => write!(f, "no global `window` exists"),
+            Error::NoDocument => write!(f, "unable to get `document` node"),
+            Error::Dom(message) => write!(f, "DOM exception: {}", message),
+            Error::Format => write!(f, "formatting error"),
+            Error::DuplicateRef(name) => write!(f, "duplicate ref '@{}'", name),
+            Error::InvalidTagName(tag) => write!(f, "invalid tag name '{}'", tag),
+            Error::NoMatch(selector) => write!(f, "no element matches '{}'", selector),
+            Error::NotAttached => write!(f, "reference node is not attached"),
+            #[cfg(feature = "dom")]
+            Error::Hook(err) => err.fmt(f)
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<fmt::Error> for Error {
+    fn from(_: fmt::Error) -> Error {
+        Error::Format
+    }
+}
+
+#[cfg(feature = "dom")]
+impl From<HookError> for Error {
+    fn from(err: HookError) -> Error {
+        Error::Hook(err)
+    }
+}
+
+/// Exceptions thrown by web-sys calls
+#[cfg(feature = "dom")]
+impl From<JsValue> for Error {
+    fn from(value: JsValue) -> Error {
+        let message = match value.dyn_ref::<js_sys::Error>() {
+            Some(err) => String::from(err.message()),
+            None => value.as_string().unwrap_or_else(|| format!("{:?}", value))
+        };
+        Error::Dom(message)
+    }
+}
+
+/// Lets `?` hand errors on to `Result<_, JsValue>` functions such as wasm-bindgen exports
+#[cfg(feature = "dom")]
+impl From<Error> for JsValue {
+    fn from(err: Error) -> JsValue {
+        JsValue::from_str(&err.to_string())
+    }
+}
@@ -1,32 +1,355 @@
+use std::cell::RefCell;
 use std::collections::BTreeMap;
-pub use crate::utils::{Element, document, ElementResult};
+use std::fmt;
+pub use crate::utils::{Element, document, ElementResult, Error};
+use crate::dom::{DomBackend, HookCast, WebBackend, Context, MemoryBackend, Binding, next_scope};
+use crate::dom::patch::Patcher;
+use crate::dom::memory::NodeKind;
+use crate::signal::{Reactive, Subscription};
+use crate::bind::FormBinding;
+use crate::Render;
 
-pub struct Html {
-    pub roots: Vec<Element>,
-    pub hooks: BTreeMap<String, Element>,
+/// Failure to access a hook with [`Html::hook`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookError {
+    /// No element was rendered with `@name`
+    Missing(String),
+    /// The element is not of the requested type
+    WrongType {
+        name: String,
+        tag: String,
+        expected: &'static str
+    }
+}
+
+impl fmt::Display for HookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HookError::Missing(name) => write!(f, "hook '{}' not found", name),
+            HookError::WrongType { name, tag, expected } => {
+                write!(f, "hook '{}' is a <{}> element, not a {}", name, tag, expected)
+            }
+        }
+    }
+}
+
+impl std::error::Error for HookError {}
+
+/// Where [`Html::mount`] places the root nodes
+pub enum MountTarget<'a, B:DomBackend = WebBackend> {
+    /// After the last child of the element
+    Append(&'a B::Element),
+    /// Before the first child of the element
+    Prepend(&'a B::Element),
+    /// Before the reference node, which must have a parent
+    Before(&'a B::Node),
+    /// After the reference node, which must have a parent
+    After(&'a B::Node),
+    /// In place of all children of the element
+    Replace(&'a B::Element),
+    /// Appended to the first element matching a CSS selector
+    Selector(&'a str)
 }
 
-impl Html {
-    pub fn new(roots : Vec<Element>, hooks : BTreeMap<String, Element>) -> ElementResult<Html> {
+pub struct Html<B:DomBackend = WebBackend> {
+    pub backend: B,
+    /// Top level nodes, including text
+    pub roots: Vec<B::Node>,
+    pub hooks: BTreeMap<String, B::Element>,
+    /// Suffix of the `data-ref` values of this instance
+    scope: u64,
+    /// Element given to the last [`Html::mount`], kept so that a view without
+    /// nodes can still be updated in place
+    parent: RefCell<Option<B::Element>>,
+    /// Attached `on:event` handlers, detached when the `Html` is dropped
+    listeners: Vec<B::Listener>,
+    /// Signal bindings, unsubscribed when the `Html` is dropped
+    subscriptions: Vec<Subscription>,
+}
+
+impl<B:DomBackend> Html<B> {
+    pub fn new(backend : B, roots : Vec<B::Node>, hooks : BTreeMap<String, B::Element>) -> ElementResult<Html<B>> {
         let html = Html {
+            backend,
             roots,
-            hooks
+            hooks,
+            scope: next_scope(),
+            parent: RefCell::new(None),
+            listeners: vec![],
+            subscriptions: vec![]
         };
         Ok(html)
     }
 
-    pub fn roots<'html>(&'html self) -> &'html Vec<Element> {
+    /// Creates the `Html` for a finished render, attaching its pending
+    /// listeners and signal bindings
+    pub fn from_context(ctx : Context<B>, roots : Vec<B::Node>) -> ElementResult<Html<B>> {
+        let mut html = Html::new(ctx.backend, roots, ctx.hooks)?;
+        html.scope = ctx.scope;
+        for (element, event, handler) in ctx.listeners {
+            let listener = html.backend.add_event_listener(&element, &event, handler)?;
+            html.listeners.push(listener);
+        }
+        for (binding, value) in ctx.bindings {
+            let subscription = html.bind(binding, value);
+            html.subscriptions.push(subscription);
+        }
+        for (element, binding) in ctx.inputs {
+            let (listener, subscription) = html.bind_input(element, binding)?;
+            html.listeners.push(listener);
+            html.subscriptions.extend(subscription);
+        }
+        Ok(html)
+    }
+
+    fn bind(&self, binding : Binding<B>, value : Box<dyn Reactive>) -> Subscription {
+        let backend = self.backend.clone();
+        // notifications have no caller to report to, a failed update is skipped
+        match binding {
+            Binding::Text(node) => value.subscribe_text(Box::new(move |text| {
+                let _ = backend.set_text(&node, &text);
+            })),
+            Binding::Attribute(element, name) => value.subscribe_text(Box::new(move |text| {
+                let _ = backend.set_attribute(&element, &name, &text);
+            }))
+        }
+    }
+
+    /// Syncs the control with its bound state and writes user edits back to it
+    fn bind_input(&self, element : B::Element, binding : FormBinding) -> ElementResult<(B::Listener, Option<Subscription>)> {
+        let backend = self.backend.clone();
+        match binding {
+            FormBinding::Value(state) => {
+                self.backend.set_value(&element, &state.get())?;
+                let subscription = state.watch(Box::new({
+                    let backend = backend.clone();
+                    let element = element.clone();
+                    move |value| {
+                        // re-setting an unchanged value would move the caret
+                        if backend.value(&element) != value {
+                            let _ = backend.set_value(&element, &value);
+                        }
+                    }
+                }));
+                let listener = self.backend.add_event_listener(&element, "input", Box::new({
+                    let element = element.clone();
+                    move |_| state.set(backend.value(&element))
+                }))?;
+                Ok((listener, subscription))
+            }
+            FormBinding::Checked(state) => {
+                self.backend.set_checked(&element, state.get())?;
+                let subscription = state.watch(Box::new({
+                    let backend = backend.clone();
+                    let element = element.clone();
+                    move |checked| {
+                        let _ = backend.set_checked(&element, checked);
+                    }
+                }));
+                let listener = self.backend.add_event_listener(&element, "change", Box::new({
+                    let element = element.clone();
+                    move |_| state.set(backend.checked(&element))
+                }))?;
+                Ok((listener, subscription))
+            }
+        }
+    }
+
+    pub fn scope(&self) -> u64 {
+        self.scope
+    }
+
+    pub fn roots(&self) -> &Vec<B::Node> {
         &self.roots
     }
 
-    pub fn hooks<'html>(&'html self) -> &'html BTreeMap<String,Element> {
+    pub fn hooks(&self) -> &BTreeMap<String,B::Element> {
         &self.hooks
     }
 
-    pub fn inject_into(&self, element : &Element) -> ElementResult<()> {
-        for root in self.roots.iter() {
-            element.append_child(&root)?;
+    /// Takes the hooks, leaving listeners and signal bindings attached for
+    /// the rest of the program, like `Closure::forget`
+    pub fn into_hooks(mut self) -> BTreeMap<String,B::Element> {
+        std::mem::forget(std::mem::take(&mut self.listeners));
+        std::mem::forget(std::mem::take(&mut self.subscriptions));
+        std::mem::take(&mut self.hooks)
+    }
+
+    /// Moves all root nodes, in order, to the end of `element`
+    pub fn inject_into(&self, element : &B::Element) -> ElementResult<()> {
+        self.mount(MountTarget::Append(element))
+    }
+
+    /// Moves all root nodes, in order, to `target`, detaching them from
+    /// where they were mounted before
+    pub fn mount(&self, target : MountTarget<B>) -> ElementResult<()> {
+        match target {
+            MountTarget::Append(element) => self.insert_roots(element, None),
+            MountTarget::Prepend(element) => {
+                let first = self.backend.child_nodes(element).into_iter().next();
+                self.insert_roots(element, first.as_ref())
+            }
+            MountTarget::Before(node) => {
+                let parent = self.reference_parent(node)?;
+                self.insert_roots(&parent, Some(node))
+            }
+            MountTarget::After(node) => {
+                let parent = self.reference_parent(node)?;
+                let next = self.backend.next_sibling(node);
+                self.insert_roots(&parent, next.as_ref())
+            }
+            MountTarget::Replace(element) => {
+                for child in self.backend.child_nodes(element) {
+                    if !self.roots.contains(&child) {
+                        self.backend.remove_child(element, &child)?;
+                    }
+                }
+                self.insert_roots(element, None)
+            }
+            MountTarget::Selector(selector) => {
+                match self.backend.query_selector(selector)? {
+                    Some(element) => self.insert_roots(&element, None),
+                    None => Err(Error::NoMatch(selector.to_string()))
+                }
+            }
         }
+    }
+
+    /// Detaches every root node and clears the hooks
+    pub fn unmount(&mut self) -> ElementResult<()> {
+        if let Some(parent) = self.parent.take() {
+            for root in self.roots.iter() {
+                if self.backend.parent(root).is_some() {
+                    self.backend.remove_child(&parent, root)?;
+                }
+            }
+        }
+        self.hooks.clear();
+        Ok(())
+    }
+
+    /// Element the view was last mounted in, `None` before the first
+    /// [`Html::mount`] and after [`Html::unmount`]
+    pub fn mounted(&self) -> Option<B::Element> {
+        self.parent.borrow().clone()
+    }
+
+    /// Records `parent` as the element the roots were rendered into
+    pub(crate) fn set_mounted(&self, parent : &B::Element) {
+        self.parent.replace(Some(parent.clone()));
+    }
+
+    fn insert_roots(&self, parent : &B::Element, reference : Option<&B::Node>) -> ElementResult<()> {
+        self.set_mounted(parent);
+        // the roots move, so they cannot serve as the reference themselves
+        let mut reference = reference.cloned();
+        while let Some(node) = reference.as_ref().filter(|node| self.roots.contains(node)) {
+            reference = self.backend.next_sibling(node);
+        }
+        // one insertion into the document for all roots
+        let fragment = self.backend.fragment(&self.roots)?;
+        self.backend.insert_before(parent, &fragment, reference.as_ref())
+    }
+
+    fn reference_parent(&self, node : &B::Node) -> ElementResult<B::Element> {
+        self.backend.parent(node)
+            .ok_or(Error::NotAttached)
+    }
+
+    /// Element registered as hook `name`
+    pub fn hook_element(&self, name : &str) -> Result<&B::Element, HookError> {
+        self.hooks.get(name).ok_or_else(|| HookError::Missing(name.to_string()))
+    }
+
+    /// Hook `name` cast to the element type `T`, e.g.
+    /// `html.hook::<web_sys::HtmlInputElement>("name")?`
+    pub fn hook<T : HookCast<B>>(&self, name : &str) -> Result<T, HookError> {
+        let element = self.hook_element(name)?;
+        T::cast(&self.backend, element).ok_or_else(|| HookError::WrongType {
+            name: name.to_string(),
+            tag: self.backend.tag_name(element),
+            expected: std::any::type_name::<T>()
+        })
+    }
+
+    /// Re-renders the view from `tree`, applying only the differences to the
+    /// current DOM nodes, so unchanged elements (and their hooks) stay in place
+    pub fn update<T:Render>(&mut self, tree:T) -> ElementResult<()> {
+        let mut ctx = Context::with_scope(MemoryBackend, self.scope);
+        let mut vroot = ctx.backend.create_element("div")?;
+        tree.render_node(&mut vroot, &mut ctx)?;
+        let vroots = ctx.backend.child_nodes(&vroot);
+
+        let mut patcher = Patcher::new(&self.backend);
+        let mut roots = Vec::with_capacity(vroots.len());
+        let keyed = vroots.iter().any(|vnode| vnode.key().is_some())
+            && vroots.iter().all(|vnode| !matches!(vnode.kind(), NodeKind::Html(_)));
+        if keyed {
+            let parent = self.mounted();
+            roots = patcher.patch_keyed_roots(parent.as_ref(), &self.roots, &vroots)?;
+        }
+        for (index, vnode) in vroots.iter().enumerate().filter(|_| !keyed) {
+            match self.roots.get(index) {
+                Some(root) => {
+                    roots.extend(patcher.patch(root, vnode)?);
+                }
+                None => {
+                    let nodes = patcher.create_nodes(vnode)?;
+                    // keep new roots next to the existing ones when mounted,
+                    // a view that had none gets them appended to its parent
+                    if let Some(parent) = self.mounted() {
+                        let next = match roots.last() {
+                            Some(last) => self.backend.next_sibling(last),
+                            None => None
+                        };
+                        for node in &nodes {
+                            self.backend.insert_before(&parent, node, next.as_ref())?;
+                        }
+                    }
+                    roots.extend(nodes);
+                }
+            }
+        }
+        for root in self.roots.iter().skip(vroots.len()).filter(|_| !keyed) {
+            if let Some(parent) = self.backend.parent(root) {
+                self.backend.remove_child(&parent, root)?;
+            }
+        }
+
+        let mut hooks = BTreeMap::new();
+        for (name, vnode) in ctx.hooks.iter() {
+            if let Some(element) = patcher.mapped(vnode) {
+                hooks.insert(name.clone(), element);
+            }
+        }
+        let mut listeners = vec![];
+        for (vnode, event, handler) in ctx.listeners {
+            if let Some(element) = patcher.mapped(&vnode) {
+                listeners.push(self.backend.add_event_listener(&element, &event, handler)?);
+            }
+        }
+        let mut subscriptions = vec![];
+        for (binding, value) in ctx.bindings {
+            let binding = match binding {
+                Binding::Text(vnode) => patcher.mapped_node(&vnode).cloned().map(Binding::Text),
+                Binding::Attribute(vnode, name) => patcher.mapped(&vnode).map(|element| Binding::Attribute(element, name))
+            };
+            if let Some(binding) = binding {
+                subscriptions.push(self.bind(binding, value));
+            }
+        }
+        for (vnode, binding) in ctx.inputs {
+            if let Some(element) = patcher.mapped(&vnode) {
+                let (listener, subscription) = self.bind_input(element, binding)?;
+                listeners.push(listener);
+                subscriptions.extend(subscription);
+            }
+        }
+        self.roots = roots;
+        self.hooks = hooks;
+        self.listeners = listeners;
+        self.subscriptions = subscriptions;
         Ok(())
     }
 }
+
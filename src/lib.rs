@@ -1,20 +1,150 @@
 pub mod render;
 pub mod escape;
+pub mod error;
+pub mod signal;
+pub mod bind;
+pub mod builder;
+pub mod attribute;
+#[cfg(feature = "dom")]
 pub mod utils;
+#[cfg(feature = "dom")]
 pub mod interface;
-pub use interface::Html;
+#[cfg(feature = "dom")]
+pub mod dom;
+#[cfg(feature = "dom")]
+pub use interface::{Html, HookError, MountTarget};
+#[cfg(feature = "dom")]
+pub use web_sys;
 
-pub use flow_html_macro::{html, tree, html_str, renderable};
-pub use render::{Render, Result, Write};
+pub use flow_html_macro::{tree, html_str, renderable, component, IntoAttribute};
+#[cfg(feature = "dom")]
+pub use flow_html_macro::html;
+pub use render::{Render, Result, Write, PreEscaped, RenderIter, Either, Borrowed};
 pub use escape::{escape_attr, escape_html};
+pub use error::{Error, ElementResult};
+pub use signal::{Signal, Effect, Subscription, Reactive, effect, computed};
+pub use bind::{Bindable, FormBinding};
+pub use builder::{PropSet, PropUnset, OptionalProp};
+pub use attribute::{IntoAttribute, AttributeText};
 use std::collections::BTreeMap;
-pub use utils::{Element as WebElement, document, ElementResult};
+use std::borrow::Cow;
+#[cfg(feature = "dom")]
+pub use utils::{Element as WebElement, document, window};
+#[cfg(feature = "dom")]
+use dom::{DomBackend, Context, Listener, Binding};
 
 
+/// Value of an attribute of a plain element, `tree!` converts the attribute
+/// expression with [`IntoAttribute::into_attribute_value`], e.g.
+/// `title={format!(..)}`, `tabindex={2}` or `class={vec!["a", "b"]}`
 #[derive(Debug)]
 pub enum AttributeValue<'a>{
     Bool(bool),
-    Str(&'a str)
+    Str(&'a str),
+    /// Computed value, e.g. from `format!`
+    Owned(String),
+    Cow(Cow<'a, str>),
+    Int(i64),
+    Float(f64),
+    /// Any other [`IntoAttribute`] value, already rendered
+    Text(Option<AttributeText<'a>>),
+    /// Left out when `None`
+    Optional(Option<Box<AttributeValue<'a>>>),
+    /// Attribute following a signal, see [`signal`]
+    Reactive(Box<dyn Reactive>)
+}
+
+impl From<bool> for AttributeValue<'_>{
+    fn from(value:bool)->Self{
+        AttributeValue::Bool(value)
+    }
+}
+
+impl<'a> From<&'a str> for AttributeValue<'a>{
+    fn from(value:&'a str)->Self{
+        AttributeValue::Str(value)
+    }
+}
+
+impl<'a> From<&&'a str> for AttributeValue<'a>{
+    fn from(value:&&'a str)->Self{
+        AttributeValue::Str(value)
+    }
+}
+
+impl<'a> From<&'a String> for AttributeValue<'a>{
+    fn from(value:&'a String)->Self{
+        AttributeValue::Str(value)
+    }
+}
+
+impl From<String> for AttributeValue<'_>{
+    fn from(value:String)->Self{
+        AttributeValue::Owned(value)
+    }
+}
+
+impl<'a> From<Cow<'a, str>> for AttributeValue<'a>{
+    fn from(value:Cow<'a, str>)->Self{
+        AttributeValue::Cow(value)
+    }
+}
+
+impl From<char> for AttributeValue<'_>{
+    fn from(value:char)->Self{
+        AttributeValue::Owned(value.to_string())
+    }
+}
+
+macro_rules! impl_from_number {
+    ($variant:ident, $target:ty, $($ty:ty),*) => {
+        $(
+            impl From<$ty> for AttributeValue<'_>{
+                fn from(value:$ty)->Self{
+                    AttributeValue::$variant(value as $target)
+                }
+            }
+        )*
+    };
+}
+
+impl_from_number!(Int, i64, i8, i16, i32, i64, u8, u16, u32);
+impl_from_number!(Float, f64, f32, f64);
+
+macro_rules! impl_from_wide_number {
+    ($($ty:ty),*) => {
+        $(
+            /// `Int` when it fits into an `i64`
+            impl From<$ty> for AttributeValue<'_>{
+                fn from(value:$ty)->Self{
+                    match i64::try_from(value){
+                        Ok(value)=>AttributeValue::Int(value),
+                        Err(_)=>AttributeValue::Owned(value.to_string())
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_from_wide_number!(u64, usize, isize, i128, u128);
+
+impl<'a, T:Into<AttributeValue<'a>>> From<Option<T>> for AttributeValue<'a>{
+    fn from(value:Option<T>)->Self{
+        AttributeValue::Optional(value.map(|value| Box::new(value.into())))
+    }
+}
+
+impl<T:std::fmt::Display+'static> From<Signal<T>> for AttributeValue<'_>{
+    fn from(value:Signal<T>)->Self{
+        AttributeValue::Reactive(Box::new(value))
+    }
+}
+
+impl<T:std::fmt::Display+'static> From<&Signal<T>> for AttributeValue<'_>{
+    fn from(value:&Signal<T>)->Self{
+        AttributeValue::Reactive(Box::new(value.clone()))
+    }
 }
 
 #[derive(Debug, Default)]
@@ -23,7 +153,23 @@ pub struct Element<'a, T:Render>{
     pub tag:&'a str,
     pub attributes:BTreeMap<&'a str, AttributeValue<'a>>,
     pub children:Option<T>,
-    pub reff:Option<(&'a str, &'a str)>
+    pub reff:Option<(&'a str, &'a str)>,
+    /// Identity of the element among its siblings, set with `key={...}`
+    pub key:Option<String>,
+    /// Form properties bound with `bind:value` / `bind:checked`
+    pub bindings:Vec<FormBinding>,
+    #[cfg(feature = "dom")]
+    pub listeners:Vec<Listener<'a>>
+}
+
+/// Elements that never have content and are written without a closing tag
+pub const VOID_ELEMENTS:[&str; 13] = [
+    "area", "base", "br", "col", "embed", "hr", "img",
+    "input", "link", "meta", "source", "track", "wbr"
+];
+
+pub fn is_void_element(tag:&str)->bool{
+    VOID_ELEMENTS.contains(&tag)
 }
 
 pub trait ElementDefaults {
@@ -39,31 +185,51 @@ pub trait ElementDefaults {
 }
 
 impl<T:Render> Render for Element<'_, T>{
-    fn render_node(self, parent:&mut WebElement, map:&mut BTreeMap<String, WebElement>)->ElementResult<()>{
-        let mut el = document()
-        .create_element(self.tag)?;
-
-        for (key, value) in &self.attributes{
-            match value{
-                AttributeValue::Bool(v)=>{
-                    if *v {
-                        el.set_attribute(key, "true")?;
-                    }
+    #[cfg(feature = "dom")]
+    fn render_node<B:DomBackend>(self, parent:&mut B::Element, ctx:&mut Context<B>)->ElementResult<()>{
+        // `<>` has no tag of its own (and `create_element("")` is an
+        // `InvalidTagName` error), its children go straight into `parent`
+        if self.is_fragment{
+            if let Some(children) = self.children{
+                children.render_node(parent, ctx)?;
+            }
+            return Ok(());
+        }
+        let mut el = ctx.backend.create_element(self.tag)?;
+
+        for (key, value) in self.attributes{
+            value.set_attribute(&ctx.backend, &el, key)?;
+            if let AttributeValue::Reactive(v) = value{
+                ctx.bind(Binding::Attribute(el.clone(), key.to_string()), v);
+            }
+        }
+        if let Some((key, value)) = self.reff{
+            ctx.set_ref(&el, key, value)?;
+        }
+        if let Some(key) = &self.key{
+            ctx.backend.set_key(&el, key)?;
+        }
+        for binding in self.bindings{
+            match &binding{
+                FormBinding::Value(state)=>{
+                    ctx.backend.set_attribute(&el, "value", &state.get())?;
                 }
-                AttributeValue::Str(v)=>{
-                    el.set_attribute(key, v)?;
+                FormBinding::Checked(state)=>{
+                    if state.get(){
+                        ctx.backend.set_attribute(&el, "checked", "true")?;
+                    }
                 }
             }
+            ctx.bind_input(&el, binding);
         }
-        if let Some((key, value)) = self.reff{
-            el.set_attribute("data-ref", value)?;
-            map.insert(key.to_string(), el.clone());
+        for listener in self.listeners{
+            ctx.add_listener(&el, listener);
         }
         if let Some(children) = self.children{
-            children.render_node(&mut el, map)?;
+            children.render_node(&mut el, ctx)?;
         }
 
-        parent.append_child(&el)?;
+        ctx.backend.append_child(parent, &ctx.backend.element_node(&el))?;
         Ok(())
     }
     fn render<W:Write>(&self, w:&mut W)->Result{
@@ -74,19 +240,34 @@ impl<T:Render> Render for Element<'_, T>{
         }else{
             write!(w, "<{}", self.tag)?;
             for (key, value) in &self.attributes{
-                match value{
-                    AttributeValue::Bool(v)=>{
-                        if *v {
-                            write!(w, " {}", key)?;
-                        }
+                if let Some(attr) = value.render_attribute(key){
+                    write!(w, " {}", attr)?;
+                }
+            }
+            // a textarea holds its value as content rather than an attribute
+            let mut content = None;
+            for binding in &self.bindings{
+                match binding{
+                    FormBinding::Value(state) if self.tag == "textarea"=>{
+                        content = Some(state.get());
+                    }
+                    FormBinding::Value(state)=>{
+                        write!(w, " value=\"{}\"", escape_attr(state.get()))?;
                     }
-                    AttributeValue::Str(v)=>{
-                        write!(w, " {}=\"{}\"", key, (*v))?;
+                    FormBinding::Checked(state)=>{
+                        if state.get(){
+                            write!(w, " checked")?;
+                        }
                     }
                 }
             }
             write!(w, ">")?;
-            if let Some(children) = &self.children{
+            if is_void_element(self.tag){
+                return Ok(());
+            }
+            if let Some(content) = content{
+                write!(w, "{}", escape_html(content))?;
+            }else if let Some(children) = &self.children{
                 children.render(w)?;
             }
             write!(w, "</{}>", self.tag)?;
@@ -104,6 +285,7 @@ mod test{
     use crate::Render;
     use crate::renderable;
     use crate::ElementDefaults;
+    use crate::{PreEscaped, RenderIter};
     #[test]
     pub fn simple_html(){
         self::print_hr("simple_html");
@@ -194,7 +376,7 @@ mod test{
         let tree = tree!{
             <div class={"abc"} ?active ?disabled ?active2={false} user data-user-name={"test-node"} &string2>
                 {123} {"hello"} {world} {num} {num} {num} {string} {true}
-                {1.2 as f64}
+                {1.2_f64}
                 <h1>{"hello 123"} {num}</h1>
                 {"10"}
                 {11}
@@ -218,7 +400,1211 @@ mod test{
         );
     }
 
-    fn print_hr<'a>(_title: &'a str){
+    #[test]
+    pub fn escaping(){
+        self::print_hr("escaping");
+        let user_input = "<script>alert(\"x\")</script>&";
+        let owned = user_input.to_string();
+        let tree = tree!{
+            <div title={user_input}>{user_input} {owned}</div>
+        };
+        let result = tree.html();
+        println!("html: {}", result);
+        assert_eq!(
+            result,
+            "<div title=\"&lt;script&gt;alert(&quot;x&quot;)&lt;/script&gt;&amp;\">&lt;script&gt;alert(\"x\")&lt;/script&gt;&amp;&lt;script&gt;alert(\"x\")&lt;/script&gt;&amp;</div>"
+        );
+
+        let tree = tree!{
+            <div>{PreEscaped("<b>trusted</b>")}</div>
+        };
+        assert_eq!(tree.html(), "<div><b>trusted</b></div>");
+    }
+
+    #[test]
+    pub fn collections(){
+        self::print_hr("collections");
+        let items = ["a".to_string(), "b<".to_string()];
+        let label:Option<String> = None;
+        let title = Some("title");
+        let tree = tree!{
+            <ul>
+                {items.iter().map(|i| tree!{<li>{i}</li>}).collect::<Vec<_>>()}
+                {label} {title} {['x', '&']}
+                {RenderIter(items.iter().map(|i| i.as_str()))}
+                {Box::new(1)} {std::rc::Rc::new("rc")} {std::borrow::Cow::from("cow")}
+                {&items[..]}
+            </ul>
+        };
+        let result = tree.html();
+        println!("html: {}", result);
+        assert_eq!(result, "<ul><li>a</li><li>b&lt;</li>titlex&amp;ab&lt;1rccowab&lt;</ul>");
+    }
+
+    #[test]
+    pub fn void_elements(){
+        self::print_hr("void_elements");
+        let src = "logo.png";
+        let tree = tree!{
+            <div>
+                "a"<br/>"b"
+                <img src={src} />
+                <input type="text" ?disabled={true}>
+                <hr></hr>
+                <span>"c"</span>
+            </div>
+        };
+        let result = tree.html();
+        println!("html: {}", result);
+        assert_eq!(result, "<div>a<br>b<img src=\"logo.png\"><input disabled type=\"text\"><hr><span>c</span></div>");
+
+        // unclosed void siblings are parsed once each
+        let tree = tree!{
+            <p>
+                <br><br><br><br><br><br><br><br><br><br><br><br>
+                <br><br><br><br><br><br><br><br><br><br><br><br>
+                <wbr></wbr>
+            </p>
+        };
+        assert_eq!(tree.html(), format!("<p>{}<wbr></p>", "<br>".repeat(24)));
+    }
+
+    #[test]
+    pub fn renderable_ref(){
+        self::print_hr("renderable_ref");
+        #[renderable(flow-input)]
+        struct FlowInput<'a, R:Render>{
+            pub value:&'a str,
+            pub placeholder:Option<String>,
+            pub children:Option<R>
+        }
+        let tree = tree!{
+            <FlowInput @input value={"abc"} />
+        };
+        assert_eq!(tree.__reff.map(|(name, _)| name), Some("input"));
+        let result = tree.html();
+        println!("html: {}", result);
+        assert_eq!(result, "<flow-input value=\"abc\"></flow-input>");
+    }
+
+    #[test]
+    pub fn renderable_reserved_fields(){
+        self::print_hr("renderable_reserved_fields");
+        // `key`, `reff` and `listeners` are ordinary field names
+        #[renderable(flow-item)]
+        struct FlowItem<R:Render>{
+            pub key:Option<&'static str>,
+            pub reff:Option<u8>,
+            pub listeners:Option<u32>,
+            pub children:Option<R>
+        }
+        let item = FlowItem::builder()
+            .key(Some("user"))
+            .reff(Some(1))
+            .children(Option::<()>::None)
+            .build();
+        assert_eq!(item.html(), "<flow-item key=\"user\" reff=\"1\"></flow-item>");
+
+        // `key={...}` in a tree stays the identity of the element
+        let tree = tree!{
+            <FlowItem key={"a"} listeners={Some(2)} />
+        };
+        assert_eq!(tree.__key.as_deref(), Some("a"));
+        assert_eq!(tree.html(), "<flow-item listeners=\"2\"></flow-item>");
+
+        #[cfg(feature = "dom")]
+        {
+            use crate::dom::MemoryBackend;
+            let html = tree.render_tree_with(MemoryBackend).unwrap();
+            assert_eq!(html.roots()[0].key().as_deref(), Some("a"));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "dom")]
+    pub fn memory_backend(){
+        self::print_hr("memory_backend");
+        use crate::dom::MemoryBackend;
+        #[renderable(flow-item)]
+        struct FlowItem<'a, R:Render>{
+            pub text:&'a str,
+            pub active:bool,
+            pub children:Option<R>
+        }
+        let name = "a<b";
+        let tree = tree!{
+            <ul class="list" @list>
+                "items: " {name}
+                <FlowItem text={"x"} active={true} @item><b>{1}</b></FlowItem>
+                <li ?hidden={false}>{PreEscaped("<i>raw</i>")}</li>
+            </ul>
+            <p></p>
+        };
+        let html = tree.render_tree_with(MemoryBackend).unwrap();
+        let roots = html.roots();
+        assert_eq!(roots.len(), 2);
+        let list = html.hooks().get("list").unwrap();
+        assert_eq!(list, &roots[0]);
+        assert!(list.attribute("data-ref").is_some());
+        let item = html.hooks().get("item").unwrap();
+        assert_eq!(item.tag().unwrap(), "flow-item");
+        assert_eq!(item.attribute("text").unwrap(), "x");
+        assert_eq!(item.attribute("active").unwrap(), "true");
+        assert_eq!(list.child_nodes().len(), 4);
+        assert_eq!(list.text_content(), "items: a<b1<i>raw</i>");
+        assert_eq!(roots[1].html(), "<p></p>");
+        let li = &list.child_nodes()[3];
+        println!("html: {}", li.html());
+        assert_eq!(li.html(), "<li><i>raw</i></li>");
+    }
+
+    #[test]
+    #[cfg(feature = "dom")]
+    pub fn update_patches_dom(){
+        self::print_hr("update_patches_dom");
+        use crate::dom::{MemoryBackend, DomBackend};
+        fn view(count:u32, active:bool, extra:bool)->impl Render{
+            let label = if active { "on" } else { "off" };
+            tree!{
+                <div class="counter" ?active={active} @counter>
+                    <span @value>{count}</span>
+                    <b>{label}</b>
+                    {extra.then(|| tree!{<i>"extra"</i>})}
+                </div>
+            }
+        }
+        let backend = MemoryBackend;
+        let container = backend.create_element("main").unwrap();
+        let mut html = view(1, true, true).render_tree_with(backend).unwrap();
+        html.inject_into(&container).unwrap();
+        let counter = html.hooks().get("counter").unwrap().clone();
+        let value = html.hooks().get("value").unwrap().clone();
+        let value_text = value.child_nodes()[0].clone();
+        assert_eq!(counter.attribute("active").unwrap(), "true");
+        assert_eq!(counter.child_nodes().len(), 3);
+
+        html.update(view(2, false, false)).unwrap();
+        println!("html: {}", container.html());
+        assert_eq!(html.hooks().get("counter").unwrap(), &counter);
+        assert_eq!(html.hooks().get("value").unwrap(), &value);
+        assert_eq!(value.child_nodes()[0], value_text);
+        assert_eq!(value_text.text_content(), "2");
+        assert_eq!(counter.attribute("active"), None);
+        assert_eq!(counter.child_nodes().len(), 2);
+        assert_eq!(counter.text_content(), "2off");
+
+        html.update(tree!{<p>"replaced"</p><p>"added"</p>}).unwrap();
+        assert_eq!(html.roots().len(), 2);
+        assert!(html.hooks().is_empty());
+        assert_eq!(container.html(), "<main><p>replaced</p><p>added</p></main>");
+    }
+
+    #[test]
+    #[cfg(feature = "dom")]
+    pub fn event_listeners(){
+        self::print_hr("event_listeners");
+        use crate::dom::MemoryBackend;
+        use std::{rc::Rc, cell::Cell};
+        let clicks = Rc::new(Cell::new(0));
+        let view = |step:u32|{
+            let clicks = clicks.clone();
+            tree!{
+                <button class="inc" @button on:click={move |_| clicks.set(clicks.get()+step)}>"+"</button>
+            }
+        };
+        assert_eq!(view(1).html(), "<button class=\"inc\">+</button>");
+
+        let mut html = view(1).render_tree_with(MemoryBackend).unwrap();
+        let button = html.hooks().get("button").unwrap().clone();
+        assert_eq!(button.listeners(), vec!["click"]);
+        button.dispatch("click");
+        button.dispatch("click");
+        assert_eq!(clicks.get(), 2);
+
+        html.update(view(10)).unwrap();
+        assert_eq!(button.listeners(), vec!["click"]);
+        button.dispatch("click");
+        assert_eq!(clicks.get(), 12);
+
+        drop(html);
+        assert!(button.listeners().is_empty());
+        button.dispatch("click");
+        assert_eq!(clicks.get(), 12);
+
+        let received = Rc::new(Cell::new(false));
+        let handler_received = received.clone();
+        let html = tree!{
+            <button @button on:click={move |event:crate::dom::Event|{
+                assert_eq!(event.event_type(), "click");
+                assert!(event.as_web().is_none());
+                handler_received.set(true);
+            }}>"ok"</button>
+        }.render_tree_with(MemoryBackend).unwrap();
+        let button = html.hooks().get("button").unwrap().clone();
+        button.dispatch("click");
+        assert!(received.get());
+    }
+
+    #[test]
+    pub fn control_flow(){
+        self::print_hr("control_flow");
+        enum Status{
+            Online,
+            Away(u32),
+            Offline
+        }
+        let view = |logged_in:bool, count:usize, status:Status|{
+            tree!{
+                <div>
+                    if logged_in {
+                        <b>"welcome"</b>
+                    } else {
+                        <a href="/login">"login"</a>
+                    }
+                    if count == 0 { "empty" } else if count == 1 { "one" } else { {count} " items" }
+                    if count > 5 { <i>"many"</i> }
+                    match status {
+                        Status::Online => <span class="on">"online"</span>,
+                        Status::Away(minutes) if minutes > 10 => { "away " {minutes} },
+                        Status::Away(_) | Status::Offline => { "offline" }
+                    }
+                </div>
+            }
+        };
+        assert_eq!(
+            view(true, 0, Status::Online).html(),
+            "<div><b>welcome</b>empty<span class=\"on\">online</span></div>"
+        );
+        assert_eq!(
+            view(false, 1, Status::Away(15)).html(),
+            "<div><a href=\"/login\">login</a>oneaway 15</div>"
+        );
+        assert_eq!(
+            view(false, 7, Status::Away(5)).html(),
+            "<div><a href=\"/login\">login</a>7 items<i>many</i>offline</div>"
+        );
+        assert_eq!(
+            view(true, 2, Status::Offline).html(),
+            "<div><b>welcome</b>2 itemsoffline</div>"
+        );
+    }
+
+    #[test]
+    pub fn for_loops(){
+        self::print_hr("for_loops");
+        struct Row{
+            id:u32,
+            name:String
+        }
+        let rows = vec![
+            Row{id:1, name:"one".to_string()},
+            Row{id:2, name:"<two>".to_string()}
+        ];
+        let tree = tree!{
+            <ul>
+                for row in rows.iter() {
+                    <li key={row.id} class="row">{row.id} ": " {&row.name}</li>
+                }
+                for (index, name) in ["a", "b"].iter().enumerate() {
+                    {index} {*name}
+                }
+            </ul>
+        };
+        assert_eq!(tree.html(), "<ul><li class=\"row\">1: one</li><li class=\"row\">2: &lt;two&gt;</li>0a1b</ul>");
+        let items = tree!{
+            for row in rows { <li key={row.id}>{row.name}</li> }
+        };
+        assert_eq!(items[1].key.as_deref(), Some("2"));
+        let keys = ["a", "b"];
+        let items = tree!{
+            for key in keys { <li key>{key}</li> }
+        };
+        assert_eq!(items[0].key.as_deref(), Some("a"));
+        assert_eq!(items.html(), "<li>a</li><li>b</li>");
+    }
+
+    #[test]
+    #[cfg(feature = "dom")]
+    pub fn for_loop_keys(){
+        self::print_hr("for_loop_keys");
+        use crate::dom::MemoryBackend;
+        let ids = [3, 1, 2];
+        let html = tree!{
+            <ul @list>
+                for id in ids { <li key={id}>{id}</li> }
+            </ul>
+        }.render_tree_with(MemoryBackend).unwrap();
+        let list = html.hooks().get("list").unwrap();
+        let keys:Vec<_> = list.child_nodes().iter().map(|li| li.key().unwrap()).collect();
+        assert_eq!(keys, vec!["3", "1", "2"]);
+        assert!(!list.html().contains("key"));
+    }
+
+    #[test]
+    #[cfg(feature = "dom")]
+    pub fn keyed_reconciliation(){
+        self::print_hr("keyed_reconciliation");
+        use crate::dom::{MemoryBackend, memory::Node};
+        #[renderable(flow-row)]
+        struct FlowRow<'a, R:Render>{
+            pub label:&'a str,
+            pub children:Option<R>
+        }
+        fn view(ids:&[u32])->impl Render + '_{
+            tree!{
+                <ul @list>
+                    <li class="header">"rows"</li>
+                    for id in ids.iter() {
+                        <li key={id} class={if id % 2 == 0 {"even"} else {"odd"}}>{*id}</li>
+                    }
+                    <FlowRow key={"footer"} label={"end"} @footer />
+                </ul>
+            }
+        }
+        let mut html = view(&[1, 2, 3, 4]).render_tree_with(MemoryBackend).unwrap();
+        let list = html.hooks().get("list").unwrap().clone();
+        let footer = html.hooks().get("footer").unwrap().clone();
+        let find = |key:&str|->Node{
+            list.child_nodes().into_iter().find(|n| n.key().as_deref() == Some(key)).unwrap()
+        };
+        let (one, three, four) = (find("1"), find("3"), find("4"));
+
+        html.update(view(&[4, 3, 5, 1])).unwrap();
+        println!("html: {}", list.html());
+        let keys:Vec<_> = list.child_nodes().iter().map(|n| n.key()).collect();
+        assert_eq!(keys, vec![None, Some("4".into()), Some("3".into()), Some("5".into()), Some("1".into()), Some("footer".into())]);
+        assert_eq!(find("4"), four);
+        assert_eq!(find("3"), three);
+        assert_eq!(find("1"), one);
+        assert_eq!(html.hooks().get("footer").unwrap(), &footer);
+        assert_eq!(
+            list.html(),
+            "<ul data-ref=\"".to_string()+&list.attribute("data-ref").unwrap()+"\"><li class=\"header\">rows</li><li class=\"even\">4</li><li class=\"odd\">3</li><li class=\"odd\">5</li><li class=\"odd\">1</li><flow-row data-ref=\""+&footer.attribute("data-ref").unwrap()+"\" label=\"end\"></flow-row></ul>"
+        );
+
+        // duplicate keys are matched in order, surplus nodes are removed
+        let duplicates = |ids:&[u32]|{
+            let ids = ids.to_vec();
+            tree!{<ol @list>for id in ids { <li key={id}>{id}</li> }</ol>}
+        };
+        let mut html = duplicates(&[1, 1, 2]).render_tree_with(MemoryBackend).unwrap();
+        let list = html.hooks().get("list").unwrap().clone();
+        html.update(duplicates(&[2])).unwrap();
+        assert_eq!(list.child_nodes().len(), 1);
+        assert_eq!(list.text_content(), "2");
+        html.update(duplicates(&[2, 2, 1])).unwrap();
+        assert_eq!(list.text_content(), "221");
+
+        // keyed top level nodes are moved as well
+        let backend = MemoryBackend;
+        let container = Node::element("main");
+        let roots = |ids:&[u32]|{
+            let ids = ids.to_vec();
+            tree!{for id in ids { <p key={id}>{id}</p> }}
+        };
+        let mut html = roots(&[1, 2, 3]).render_tree_with(backend).unwrap();
+        html.inject_into(&container).unwrap();
+        let nodes = container.child_nodes();
+        html.update(roots(&[3, 1, 2])).unwrap();
+        assert_eq!(container.html(), "<main><p>3</p><p>1</p><p>2</p></main>");
+        assert_eq!(container.child_nodes(), vec![nodes[2].clone(), nodes[0].clone(), nodes[1].clone()]);
+        assert_eq!(html.roots, container.child_nodes());
+    }
+
+    #[test]
+    pub fn signals(){
+        self::print_hr("signals");
+        use crate::{Signal, effect, computed};
+        use std::rc::Rc;
+        use std::cell::RefCell;
+        let count = Signal::new(1);
+        let double = computed({
+            let count = count.clone();
+            move || count.get() * 2
+        });
+        let log = Rc::new(RefCell::new(vec![]));
+        let watcher = effect({
+            let log = log.clone();
+            let double = double.clone();
+            move || log.borrow_mut().push(double.get())
+        });
+        count.set(2);
+        count.update(|c| *c += 1);
+        assert_eq!(double.get(), 6);
+        assert_eq!(*log.borrow(), vec![2, 4, 6]);
+        drop(watcher);
+        count.set(4);
+        assert_eq!(*log.borrow(), vec![2, 4, 6]);
+
+        let label = Signal::new("<a>".to_string());
+        let tree = tree!{<span class={&label}>{count.clone()}" / "{&label}</span>};
+        assert_eq!(tree.html(), "<span class=\"&lt;a&gt;\">4 / &lt;a&gt;</span>");
+
+        // an effect may write back to the signal it reads
+        let clamp = effect({
+            let count = count.clone();
+            move ||{
+                if count.get() > 10{
+                    count.set(10);
+                }
+            }
+        });
+        let seen = Rc::new(RefCell::new(vec![]));
+        let _subscription = count.subscribe({
+            let seen = seen.clone();
+            move |value| seen.borrow_mut().push(*value)
+        });
+        count.set(20);
+        assert_eq!(count.get(), 10);
+        assert_eq!(double.get(), 20);
+        assert_eq!(seen.borrow().last(), Some(&10));
+        drop(clamp);
+    }
+
+    #[test]
+    #[cfg(feature = "dom")]
+    pub fn signal_bindings(){
+        self::print_hr("signal_bindings");
+        use crate::dom::MemoryBackend;
+        use crate::Signal;
+        let count = Signal::new(0);
+        let class = Signal::new("idle");
+        let view = |extra:bool| tree!{
+            <div @root class={&class}>
+                <b>"count: "{&count}</b>
+                if extra { <i>"extra"</i> }
+            </div>
+        };
+        let mut html = view(false).render_tree_with(MemoryBackend).unwrap();
+        let root = html.hooks().get("root").unwrap().clone();
+        let bold = root.child_nodes()[0].clone();
+        assert_eq!(bold.html(), "<b>count: 0</b>");
+
+        count.set(5);
+        class.set("busy");
+        assert_eq!(bold.html(), "<b>count: 5</b>");
+        assert_eq!(root.attribute("class").unwrap(), "busy");
+
+        // bindings follow the patched nodes
+        html.update(view(true)).unwrap();
+        count.set(6);
+        assert_eq!(root.child_nodes()[0], bold);
+        assert_eq!(bold.html(), "<b>count: 6</b>");
+        assert_eq!(root.child_nodes()[1].html(), "<i>extra</i>");
+
+        drop(html);
+        count.set(7);
+        class.set("done");
+        assert_eq!(bold.html(), "<b>count: 6</b>");
+        assert_eq!(root.attribute("class").unwrap(), "busy");
+    }
+
+    #[test]
+    pub fn form_bindings_html(){
+        self::print_hr("form_bindings_html");
+        use crate::Signal;
+        use std::rc::Rc;
+        use std::cell::{Cell, RefCell};
+        let name = Signal::new("a \"b\"".to_string());
+        let agree = Rc::new(Cell::new(true));
+        let notes = Rc::new(RefCell::new("</textarea> & more".to_string()));
+        let tree = tree!{
+            <input type="text" bind:value={&name} />
+            <input type="checkbox" bind:checked={agree} />
+            <textarea bind:value={notes}></textarea>
+        };
+        assert_eq!(
+            tree.html(),
+            "<input type=\"text\" value=\"a &quot;b&quot;\"><input type=\"checkbox\" checked><textarea>&lt;/textarea&gt; &amp; more</textarea>"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "dom")]
+    pub fn form_bindings(){
+        self::print_hr("form_bindings");
+        use crate::dom::MemoryBackend;
+        use crate::Signal;
+        use std::rc::Rc;
+        use std::cell::Cell;
+        let name = Signal::new("alice".to_string());
+        let agree = Rc::new(Cell::new(false));
+        let view = || tree!{
+            <form>
+                <input @name bind:value={&name} />
+                <input @agree type="checkbox" bind:checked={&agree} />
+            </form>
+        };
+        let mut html = view().render_tree_with(MemoryBackend).unwrap();
+        let input = html.hooks().get("name").unwrap().clone();
+        let checkbox = html.hooks().get("agree").unwrap().clone();
+        assert_eq!(input.value(), "alice");
+        assert!(!checkbox.checked());
+
+        // user edits are written back
+        input.set_value("bob");
+        input.dispatch("input");
+        assert_eq!(name.get(), "bob");
+        checkbox.set_checked(true);
+        checkbox.dispatch("change");
+        assert!(agree.get());
+
+        // signal changes reach the control
+        name.set("carol".to_string());
+        assert_eq!(input.value(), "carol");
+
+        html.update(view()).unwrap();
+        input.set_value("dave");
+        input.dispatch("input");
+        assert_eq!(name.get(), "dave");
+        assert_eq!(input.listeners(), vec!["input".to_string()]);
+
+        drop(html);
+        input.set_value("eve");
+        input.dispatch("input");
+        assert_eq!(name.get(), "dave");
+    }
+
+    #[test]
+    #[cfg(feature = "dom")]
+    pub fn typed_refs(){
+        self::print_hr("typed_refs");
+        use crate::{html, HookError, ElementResult};
+        use crate::dom::{MemoryBackend, memory};
+        use web_sys::{HtmlInputElement, HtmlElement};
+
+        // `html!` needs a browser document, the generated struct is only type checked here
+        #[allow(dead_code)]
+        fn login(show_remember:bool)->ElementResult<()>{
+            let refs = html!{@refs
+                <form>
+                    <input @user type="text" />
+                    <flow-menu @menu-list />
+                    if show_remember { <input @remember type="checkbox" /> }
+                </form>
+            }?;
+            let _:&HtmlInputElement = &refs.user;
+            let _:&HtmlElement = &refs.menu_list;
+            let _:&Option<HtmlInputElement> = &refs.remember;
+            refs.inject_into(&crate::document()?.body().unwrap())?;
+            let _:HtmlInputElement = refs.html.hook("user")?;
+            Ok(())
+        }
+
+        let html = tree!{<div @root><input @name /></div>}.render_tree_with(MemoryBackend).unwrap();
+        assert!(html.hook_element("root").is_ok());
+        let root:memory::Node = html.hook("root").unwrap();
+        assert_eq!(root.tag().as_deref(), Some("div"));
+        let name:memory::Input = html.hook("name").unwrap();
+        assert_eq!(name.tag().as_deref(), Some("input"));
+        let missing = html.hook::<memory::Input>("other").err().unwrap();
+        assert_eq!(missing, HookError::Missing("other".into()));
+        assert_eq!(missing.to_string(), "hook 'other' not found");
+        let wrong = html.hook::<memory::Input>("root").err().unwrap();
+        assert_eq!(wrong, HookError::WrongType{
+            name:"root".into(),
+            tag:"div".into(),
+            expected:std::any::type_name::<memory::Input>()
+        });
+        assert_eq!(wrong.to_string(), "hook 'root' is a <div> element, not a flow_html::dom::memory::Input");
+    }
+
+    #[test]
+    pub fn deterministic_refs(){
+        self::print_hr("deterministic_refs");
+        let view = || tree!{<div @a><span @b></span></div>};
+        let first = view();
+        let second = view();
+        let again = tree!{<div @a><span @b></span></div>};
+        let a = first.reff.unwrap().1;
+        assert!(a.starts_with("ref_"));
+        assert_eq!(second.reff.unwrap().1, a);
+        // identical markup at another call site does not collide
+        assert_ne!(again.reff.unwrap().1, a);
+        let b = first.children.unwrap().reff.unwrap().1;
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    #[cfg(feature = "dom")]
+    pub fn scoped_refs(){
+        self::print_hr("scoped_refs");
+        use crate::dom::MemoryBackend;
+        let view = |text:&'static str| tree!{<div @root>{text}</div>};
+        let mut first = view("a").render_tree_with(MemoryBackend).unwrap();
+        let second = view("a").render_tree_with(MemoryBackend).unwrap();
+        let root = first.hooks().get("root").unwrap().clone();
+        let data_ref = root.attribute("data-ref").unwrap();
+        assert_ne!(data_ref, second.hooks().get("root").unwrap().attribute("data-ref").unwrap());
+        assert!(data_ref.ends_with(&format!("-{}", first.scope())));
+
+        first.update(view("b")).unwrap();
+        assert_eq!(first.hooks().get("root").unwrap(), &root);
+        assert_eq!(root.attribute("data-ref").unwrap(), data_ref);
+    }
+
+    #[test]
+    #[cfg(feature = "dom")]
+    pub fn text_roots(){
+        self::print_hr("text_roots");
+        use crate::dom::{MemoryBackend, DomBackend};
+        let view = |name:&'static str, raw:bool| tree!{
+            "hello "
+            <b>{name}</b>
+            if raw { {PreEscaped("<i>!</i>")} }
+        };
+        let mut html = view("x", false).render_tree_with(MemoryBackend).unwrap();
+        assert_eq!(html.roots().len(), 2);
+        assert_eq!(html.roots()[0].text_content(), "hello ");
+        assert!(html.roots()[0].parent().is_none());
+
+        let container = MemoryBackend.create_element("main").unwrap();
+        html.inject_into(&container).unwrap();
+        assert_eq!(container.html(), "<main>hello <b>x</b></main>");
+
+        let greeting = html.roots()[0].clone();
+        html.update(view("y", true)).unwrap();
+        assert_eq!(container.html(), "<main>hello <b>y</b><i>!</i></main>");
+        assert_eq!(html.roots()[0], greeting);
+        assert_eq!(html.roots().len(), 3);
+
+        html.update(view("z", false)).unwrap();
+        assert_eq!(container.html(), "<main>hello <b>z</b></main>");
+    }
+
+    #[test]
+    #[cfg(feature = "dom")]
+    pub fn mounting(){
+        self::print_hr("mounting");
+        use crate::dom::{MemoryBackend, DomBackend};
+        use crate::MountTarget;
+        let backend = MemoryBackend;
+        let main = backend.create_element("main").unwrap();
+        let aside = backend.create_element("aside").unwrap();
+        let marker = backend.create_text("|").unwrap();
+        backend.append_child(&main, &marker).unwrap();
+        backend.append_text(&aside, "old").unwrap();
+
+        let mut html = tree!{"a"<b @b>"b"</b>}.render_tree_with(backend).unwrap();
+        assert!(html.mounted().is_none());
+        html.mount(MountTarget::Prepend(&main)).unwrap();
+        assert_eq!(main.html(), "<main>a<b data-ref=\"".to_string()+&html.hooks()["b"].attribute("data-ref").unwrap()+"\">b</b>|</main>");
+        assert_eq!(html.mounted().unwrap(), main);
+        // mounting again moves the roots
+        html.mount(MountTarget::After(&marker)).unwrap();
+        assert_eq!(main.text_content(), "|ab");
+        html.mount(MountTarget::Before(&marker)).unwrap();
+        assert_eq!(main.text_content(), "ab|");
+        html.mount(MountTarget::Prepend(&main)).unwrap();
+        assert_eq!(main.text_content(), "ab|");
+        html.mount(MountTarget::Replace(&aside)).unwrap();
+        assert_eq!(aside.text_content(), "ab");
+        assert_eq!(main.text_content(), "|");
+        assert_eq!(html.mounted().unwrap(), aside);
+        html.mount(MountTarget::Append(&main)).unwrap();
+        assert_eq!(main.text_content(), "|ab");
+
+        html.unmount().unwrap();
+        assert_eq!(main.html(), "<main>|</main>");
+        assert!(html.mounted().is_none());
+        assert!(html.hooks().is_empty());
+
+        // a view without nodes remembers where it was mounted
+        let view = |items:&[&'static str]|{
+            let items = items.to_vec();
+            tree!{for item in items { <i>{item}</i> }}
+        };
+        let mut html = view(&[]).render_tree_with(MemoryBackend).unwrap();
+        html.mount(MountTarget::Append(&aside)).unwrap();
+        assert_eq!(html.mounted().unwrap(), aside);
+        html.update(view(&["x", "y"])).unwrap();
+        assert_eq!(aside.html(), "<aside><i>x</i><i>y</i></aside>");
+        html.update(view(&[])).unwrap();
+        assert_eq!(aside.html(), "<aside></aside>");
+        html.update(view(&["z"])).unwrap();
+        assert_eq!(aside.html(), "<aside><i>z</i></aside>");
+        html.unmount().unwrap();
+        assert_eq!(aside.html(), "<aside></aside>");
+    }
+
+    #[test]
+    pub fn try_html(){
+        self::print_hr("try_html");
+        use crate::{Error, Write};
+        struct Broken;
+        impl Render for Broken{
+            fn render<W:Write>(&self, _w:&mut W)->crate::Result{
+                Err(std::fmt::Error)
+            }
+        }
+        assert_eq!(tree!{<p>"ok"</p>}.try_html(), Ok("<p>ok</p>".to_string()));
+        let err = tree!{<p>{Broken}</p>}.try_html().unwrap_err();
+        assert_eq!(err, Error::Format);
+        assert_eq!(err.to_string(), "formatting error");
+    }
+
+    #[test]
+    #[cfg(feature = "dom")]
+    pub fn dom_errors(){
+        self::print_hr("dom_errors");
+        use crate::dom::{MemoryBackend, DomBackend};
+        use crate::{Error, MountTarget};
+        let tree = tree!{
+            <div>
+                {tree!{<b @item></b>}}
+                {tree!{<i @item></i>}}
+            </div>
+        };
+        let err = tree.render_tree_with(MemoryBackend).err().unwrap();
+        assert_eq!(err, Error::DuplicateRef("item".into()));
+        assert_eq!(err.to_string(), "duplicate ref '@item'");
+
+        assert_eq!(MemoryBackend.create_element("1st").err(), Some(Error::InvalidTagName("1st".into())));
+        assert!(MemoryBackend.create_element("flow-menu").is_ok());
+
+        let html = tree!{<>"a"<b>"b"</b></>}.render_tree_with(MemoryBackend).unwrap();
+        assert_eq!(html.roots().len(), 2);
+        let detached = MemoryBackend.create_text("x").unwrap();
+        assert_eq!(html.mount(MountTarget::Before(&detached)).err(), Some(Error::NotAttached));
+        assert_eq!(html.mount(MountTarget::Selector("#app")).err(), Some(Error::NoMatch("#app".into())));
+    }
+
+    #[test]
+    pub fn function_components(){
+        self::print_hr("function_components");
+        use crate::component;
+
+        #[component]
+        fn Card(title:&str, footer:Option<String>, children:impl Render)->impl Render{
+            tree!{
+                <section class="card">
+                    <h2>{title}</h2>
+                    {children}
+                    {footer.map(|text| tree!{<footer>{text}</footer>})}
+                </section>
+            }
+        }
+
+        #[component]
+        fn Badge(count:u32)->impl Render{
+            tree!{<span class="badge">{count}</span>}
+        }
+
+        let title = "a & b".to_string();
+        let tree = tree!{
+            <div>
+                <Card title={&title}>
+                    <p>"body"</p>
+                    <Badge count={3} />
+                </Card>
+                <Card title="second" footer={Some("end".to_string())} />
+            </div>
+        };
+        let expected = "<div><section class=\"card\"><h2>a &amp; b</h2><p>body</p><span class=\"badge\">3</span></section><section class=\"card\"><h2>second</h2><footer>end</footer></section></div>";
+        assert_eq!(tree.html(), expected);
+        // the component function can still be called directly
+        assert_eq!(Badge(7).html(), "<span class=\"badge\">7</span>");
+
+        #[cfg(feature = "dom")]
+        {
+            use crate::dom::MemoryBackend;
+            let html = tree.render_tree_with(MemoryBackend).unwrap();
+            assert_eq!(html.roots()[0].html(), expected);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "dom")]
+    pub fn component_keys(){
+        self::print_hr("component_keys");
+        use crate::component;
+        use crate::dom::{MemoryBackend, memory::Node};
+        use std::{rc::Rc, cell::Cell};
+
+        #[component]
+        fn Item(label:String)->impl Render{
+            tree!{<li class="item">{label}</li>}
+        }
+
+        let clicks = Rc::new(Cell::new(0));
+        let view = |ids:&[u32]|{
+            let ids = ids.to_vec();
+            let clicks = clicks.clone();
+            tree!{
+                <ul @list>
+                    for id in ids { <Item key={id} label={id.to_string()} /> }
+                    <Item @last on:click={move |_| clicks.set(clicks.get()+1)} label={"end".to_string()} />
+                </ul>
+            }
+        };
+        assert_eq!(view(&[1]).html(), "<ul><li class=\"item\">1</li><li class=\"item\">end</li></ul>");
+
+        let mut html = view(&[1, 2, 3]).render_tree_with(MemoryBackend).unwrap();
+        let list = html.hooks().get("list").unwrap().clone();
+        let last = html.hooks().get("last").unwrap().clone();
+        assert_eq!(last.text_content(), "end");
+        last.dispatch("click");
+        assert_eq!(clicks.get(), 1);
+
+        let find = |key:&str|->Node{
+            list.child_nodes().into_iter().find(|n| n.key().as_deref() == Some(key)).unwrap()
+        };
+        let (one, three) = (find("1"), find("3"));
+        html.update(view(&[3, 1])).unwrap();
+        assert_eq!(list.text_content(), "31end");
+        assert_eq!(find("3"), three);
+        assert_eq!(find("1"), one);
+    }
+
+    #[test]
+    pub fn renderable_view(){
+        self::print_hr("renderable_view");
+        #[renderable(flow-panel, view)]
+        struct FlowPanel<'a, R:Render>{
+            pub heading:&'a str,
+            pub children:Option<R>
+        }
+        impl<'a, R:Render> FlowPanel<'a, R>{
+            fn view<'s, C:Render+'s>(&'s self, children:C)->impl Render+'s{
+                tree!{
+                    <header>{self.heading}</header>
+                    <div class="body">{children}</div>
+                }
+            }
+        }
+        #[renderable(flow-tag, view = content)]
+        struct FlowTag<R:Render>{
+            pub children:Option<R>
+        }
+        impl<R:Render> FlowTag<R>{
+            fn content<C:Render>(&self, children:C)->impl Render{
+                tree!{<b>{children}</b>}
+            }
+        }
+
+        let tree = tree!{
+            <FlowPanel heading={"<news>"}>
+                <p>"text"</p>
+                <FlowTag>"new"</FlowTag>
+            </FlowPanel>
+        };
+        let expected = "<flow-panel heading=\"&lt;news&gt;\"><header>&lt;news&gt;</header><div class=\"body\"><p>text</p><flow-tag><b>new</b></flow-tag></div></flow-panel>";
+        assert_eq!(tree.html(), expected);
+
+        #[cfg(feature = "dom")]
+        {
+            use crate::dom::MemoryBackend;
+            let html = tree.render_tree_with(MemoryBackend).unwrap();
+            assert_eq!(
+                html.roots()[0].html(),
+                "<flow-panel heading=\"&lt;news&gt;\"><header>&lt;news&gt;</header><div class=\"body\"><p>text</p><flow-tag><b>new</b></flow-tag></div></flow-panel>"
+            );
+        }
+    }
+
+    #[test]
+    pub fn named_slots(){
+        self::print_hr("named_slots");
+        use crate::component;
+
+        #[renderable(flow-dialog, view)]
+        struct FlowDialog<R:Render, H:Render, F:Render>{
+            #[slot]
+            pub header:Option<H>,
+            #[slot]
+            pub footer:Option<F>,
+            pub children:Option<R>
+        }
+        impl<R:Render, H:Render, F:Render> FlowDialog<R, H, F>{
+            fn view<C:Render, SH:Render, SF:Render>(&self, children:C, header:SH, footer:SF)->impl Render{
+                tree!{
+                    <header>{header}</header>
+                    <main>{children}</main>
+                    <footer>{footer}</footer>
+                }
+            }
+        }
+
+        #[renderable(flow-card)]
+        struct FlowCard<R:Render, T:Render>{
+            #[slot]
+            pub title:Option<T>,
+            pub children:Option<R>
+        }
+
+        #[component]
+        fn Layout(#[slot] aside:impl Render, children:impl Render)->impl Render{
+            tree!{<div class="layout"><aside>{aside}</aside>{children}</div>}
+        }
+
+        let tree = tree!{
+            <div>
+                <FlowDialog>
+                    <slot:footer><button>"Ok"</button></slot:footer>
+                    <p>"body"</p>
+                    <slot:header>"Title"</slot:header>
+                </FlowDialog>
+                <FlowDialog>
+                    "only body"
+                </FlowDialog>
+                <FlowCard>
+                    <slot:title><b>"card"</b></slot:title>
+                    "text"
+                </FlowCard>
+                <Layout>
+                    <slot:aside>"menu"</slot:aside>
+                    <p>"content"</p>
+                </Layout>
+            </div>
+        };
+        let expected = concat!(
+            "<div>",
+            "<flow-dialog><header>Title</header><main><p>body</p></main><footer><button>Ok</button></footer></flow-dialog>",
+            "<flow-dialog><header></header><main>only body</main><footer></footer></flow-dialog>",
+            "<flow-card><b>card</b>text</flow-card>",
+            "<div class=\"layout\"><aside>menu</aside><p>content</p></div>",
+            "</div>"
+        );
+        assert_eq!(tree.html(), expected);
+
+        #[cfg(feature = "dom")]
+        {
+            use crate::dom::MemoryBackend;
+            let html = tree.render_tree_with(MemoryBackend).unwrap();
+            assert_eq!(html.roots()[0].html(), expected);
+        }
+    }
+
+    #[test]
+    pub fn prop_builder(){
+        self::print_hr("prop_builder");
+        use crate::component;
+
+        #[renderable(flow-button)]
+        struct FlowButton<'a, R:Render>{
+            pub label:&'a str,
+            #[prop(default = "button".to_string())]
+            #[attr(name="type")]
+            pub kind:String,
+            #[prop(default = "md")]
+            pub size:&'a str,
+            pub disabled:bool,
+            pub children:Option<R>
+        }
+
+        #[component]
+        fn Counter(count:u32, #[prop(default = "items")] unit:&'static str)->impl Render{
+            tree!{<span>{count} " " {unit}</span>}
+        }
+
+        let tree = tree!{
+            <div>
+                <FlowButton label="ok" />
+                <FlowButton label="send" kind={"submit".to_string()} size="lg" ?disabled={true} />
+                <Counter count={3} />
+                <Counter count={1} unit="item" />
+            </div>
+        };
+        assert_eq!(
+            tree.html(),
+            "<div><flow-button label=\"ok\" type=\"button\" size=\"md\"></flow-button><flow-button label=\"send\" type=\"submit\" size=\"lg\" disabled></flow-button><span>3 items</span><span>1 item</span></div>"
+        );
+
+        // the builder can also be used directly
+        let button = FlowButton::builder()
+            .label("x")
+            .children(Option::<()>::None)
+            .build();
+        assert_eq!(button.kind, "button");
+        assert_eq!(Counter::builder().count(5).build().html(), "<span>5 items</span>");
+
+        // optionality follows the type, not its name
+        type Flag = bool;
+        #[component]
+        fn Badge(text:&'static str, muted:Flag)->impl Render{
+            tree!{<em ?muted={muted}>{text}</em>}
+        }
+        assert_eq!(tree!{<Badge text="a" />}.html(), "<em>a</em>");
+        assert_eq!(tree!{<Badge text="b" muted={true} />}.html(), "<em muted>b</em>");
+    }
+
+    #[test]
+    pub fn field_options(){
+        self::print_hr("field_options");
+        use std::collections::BTreeMap;
+
+        #[renderable(flow-field, rename_all = "kebab-case")]
+        struct FlowField<'a, R:Render>{
+            pub aria_label:&'a str,
+            #[attr(name="for")]
+            pub target_id:&'a str,
+            #[attr(data)]
+            pub user_id:&'a str,
+            #[attr(skip)]
+            pub clicks:u32,
+            #[attr(flatten)]
+            pub extra:BTreeMap<String, String>,
+            pub children:Option<R>
+        }
+
+        #[renderable(flow-caption, rename_all = "camelCase")]
+        struct FlowCaption<'a, R:Render>{
+            pub max_lines:&'a str,
+            pub children:Option<R>
+        }
+
+        let mut extra = BTreeMap::new();
+        extra.insert("title".to_string(), "a \"quote\"".to_string());
+        extra.insert("role".to_string(), "group".to_string());
+        // not attribute names, left out
+        extra.insert("x\"><script>".to_string(), "alert(1)".to_string());
+        extra.insert("".to_string(), "empty".to_string());
+        let tree = tree!{
+            <div>
+                <FlowField aria_label="name" target_id="input" user_id="7" extra={extra.clone()} />
+                <FlowCaption max_lines="2" />
+            </div>
+        };
+        let expected = "<div><flow-field aria-label=\"name\" for=\"input\" data-user-id=\"7\" role=\"group\" title=\"a &quot;quote&quot;\"></flow-field><flow-caption maxLines=\"2\"></flow-caption></div>";
+        assert_eq!(tree.html(), expected);
+
+        let field = FlowField::builder()
+            .aria_label("x")
+            .target_id("y")
+            .user_id("z")
+            .clicks(3)
+            .children(Option::<()>::None)
+            .build();
+        assert_eq!(field.clicks, 3);
+        assert_eq!(field.html(), "<flow-field aria-label=\"x\" for=\"y\" data-user-id=\"z\"></flow-field>");
+
+        #[cfg(feature = "dom")]
+        {
+            use crate::dom::MemoryBackend;
+            let html = tree.render_tree_with(MemoryBackend).unwrap();
+            let field = &html.roots()[0].child_nodes()[0];
+            assert_eq!(field.attribute("data-user-id").unwrap(), "7");
+            assert_eq!(field.attribute("title").unwrap(), "a \"quote\"");
+            assert!(field.attribute("clicks").is_none());
+            assert_eq!(field.attributes().len(), 5);
+        }
+    }
+
+    #[test]
+    pub fn into_attribute(){
+        self::print_hr("into_attribute");
+        use crate::IntoAttribute;
+
+        #[derive(Debug, IntoAttribute)]
+        enum Variant{
+            Primary,
+            ExtraLarge,
+            #[attr(name = "ghost-btn")]
+            Ghost
+        }
+
+        #[derive(Debug, IntoAttribute)]
+        #[attr(rename_all = "UPPERCASE")]
+        enum Method{
+            Get
+        }
+
+        type Label = std::string::String;
+
+        #[renderable(flow-badge)]
+        struct FlowBadge<R:Render>{
+            pub count:u32,
+            pub ratio:f64,
+            pub label:Label,
+            pub open:Option<bool>,
+            pub hidden:Option<bool>,
+            pub title:Option<&'static str>,
+            pub class:Vec<String>,
+            pub variant:Variant,
+            #[prop(default)]
+            pub method:Option<Method>,
+            pub children:Option<R>
+        }
+
+        let tree = tree!{
+            <FlowBadge count={3} ratio={0.5} label={"a<b".to_string()} open={Some(true)}
+                hidden={Some(false)} class={vec!["one".to_string(), "two".to_string()]}
+                variant={Variant::ExtraLarge} method={Some(Method::Get)} />
+        };
+        let expected = "<flow-badge count=\"3\" ratio=\"0.5\" label=\"a&lt;b\" open class=\"one two\" variant=\"extra-large\" method=\"GET\"></flow-badge>";
+        assert_eq!(tree.html(), expected);
+
+        assert_eq!(Variant::Primary.render_attribute("v").unwrap(), "v=\"primary\"");
+        assert_eq!(Variant::Ghost.render_attribute("v").unwrap(), "v=\"ghost-btn\"");
+        assert_eq!(Vec::<String>::new().render_attribute("class"), None);
+        assert_eq!(["a", "b"].render_attribute("class").unwrap(), "class=\"a b\"");
+        assert_eq!(None::<u8>.render_attribute("x"), None);
+
+        // plain elements go through the same trait
+        let size = Variant::Primary;
+        let plain = tree!{
+            <div data-v={Variant::Ghost} &data-size={size} class={vec!["a", "b"]} data-empty={Vec::<&str>::new()}>
+                <button class={["x", "y"]} ?disabled={false}>"ok"</button>
+            </div>
+        };
+        let expected = "<div class=\"a b\" data-size=\"primary\" data-v=\"ghost-btn\"><button class=\"x y\">ok</button></div>";
+        assert_eq!(plain.html(), expected);
+
+        #[cfg(feature = "dom")]
+        {
+            use crate::dom::MemoryBackend;
+            let html = tree.render_tree_with(MemoryBackend).unwrap();
+            let badge = &html.roots()[0];
+            assert_eq!(badge.attribute("count").unwrap(), "3");
+            assert_eq!(badge.attribute("open").unwrap(), "true");
+            assert!(badge.attribute("hidden").is_none());
+            assert!(badge.attribute("title").is_none());
+            assert_eq!(badge.attribute("class").unwrap(), "one two");
+
+            let html = plain.render_tree_with(MemoryBackend).unwrap();
+            let div = &html.roots()[0];
+            assert_eq!(div.attribute("data-v").unwrap(), "ghost-btn");
+            assert_eq!(div.attribute("class").unwrap(), "a b");
+            assert!(div.attribute("data-empty").is_none());
+        }
+    }
+
+    #[test]
+    pub fn typed_attribute_values(){
+        self::print_hr("typed_attribute_values");
+        use std::borrow::Cow;
+        use crate::AttributeValue;
+
+        let id = 7;
+        let title:Option<&str> = None;
+        let label = Cow::Borrowed("cow");
+        let tree = tree!{
+            <ul id={format!("list-{}", id)} tabindex={2} data-ratio={0.25} title={title}
+                aria-label={label} data-size={u64::MAX} hidden={false} data-sep={'|'}>
+                <li data-page={Some(3)} class={Some("on".to_string())}>"a"</li>
+            </ul>
+        };
+        let expected = "<ul aria-label=\"cow\" data-ratio=\"0.25\" data-sep=\"|\" data-size=\"18446744073709551615\" id=\"list-7\" tabindex=\"2\"><li class=\"on\" data-page=\"3\">a</li></ul>";
+        assert_eq!(tree.html(), expected);
+
+        assert!(matches!(AttributeValue::from(2), AttributeValue::Int(2)));
+        assert!(matches!(AttributeValue::from(u64::MAX), AttributeValue::Owned(_)));
+        assert!(matches!(AttributeValue::from(None::<u8>), AttributeValue::Optional(None)));
+
+        #[cfg(feature = "dom")]
+        {
+            use crate::dom::MemoryBackend;
+            let html = tree.render_tree_with(MemoryBackend).unwrap();
+            let list = &html.roots()[0];
+            assert_eq!(list.attribute("id").unwrap(), "list-7");
+            assert_eq!(list.attribute("tabindex").unwrap(), "2");
+            assert!(list.attribute("title").is_none());
+            assert!(list.attribute("hidden").is_none());
+            assert_eq!(list.child_nodes()[0].attribute("data-page").unwrap(), "3");
+        }
+    }
+
+    fn print_hr(_title: &str){
         //println!("\n☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁☁\n");
         println!("\n☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰☰\n")
     }
@@ -1,7 +1,17 @@
 pub use std::fmt::{Result, Write};
-use crate::utils::{ElementResult, Element, document};
-use std::collections::BTreeMap;
+#[cfg(feature = "dom")]
+use crate::utils::{ElementResult, Element};
+#[cfg(feature = "dom")]
+use crate::dom::{DomBackend, Context, WebBackend};
+use std::borrow::Cow;
+use std::rc::Rc;
+use std::sync::Arc;
+#[cfg(feature = "dom")]
 use crate::Html;
+#[cfg(feature = "dom")]
+use std::collections::BTreeMap;
+use crate::escape::escape_html;
+use crate::error::Error;
 
 
 /*
@@ -10,33 +20,54 @@ pub trait RenderBase:Sized{
 }
 */
 pub trait Render:Sized{
+    /// Html output, panics if a `render` implementation fails, see [`Render::try_html`]
     fn html(&self)->String{
+        self.try_html().unwrap()
+    }
+    fn try_html(&self)->std::result::Result<String, Error>{
         let mut buf = String::from("");
-        self.render(&mut buf).unwrap();
-        buf
+        self.render(&mut buf)?;
+        Ok(buf)
     }
     // fn render_tree(self)->ElementResult<(Vec<Element>, BTreeMap<String, Element>)>{
+    #[cfg(feature = "dom")]
     fn render_tree(self)->ElementResult<Html>{
-        let mut parent = document().create_element("div").unwrap();
-        //parent.set_attribute("class", "temp-root")?;
-        let map = self.render_tree_into(&mut parent)?;
-        let mut list = vec![];
-        let children = parent.children();
-        let len = children.length();
-        for index in 0..len{
-            if let Some(child) = children.get_with_index(index){
-                list.push(child);
-            }
-        }
-        Ok(Html::new(list, map)?)
+        self.render_tree_with(WebBackend::new()?)
+    }
+    /// Renders into a document fragment, every top level node (text
+    /// included) becomes a root of the returned `Html`.
+    ///
+    /// `render_node` needs an element to append to, so the nodes are built in
+    /// a detached element and then held by a fragment until they are mounted.
+    #[cfg(feature = "dom")]
+    fn render_tree_with<B:DomBackend>(self, backend:B)->ElementResult<Html<B>>{
+        let mut parent = backend.create_element("div")?;
+        let mut ctx = Context::new(backend);
+        self.render_node(&mut parent, &mut ctx)?;
+        let list = ctx.backend.child_nodes(&parent);
+        ctx.backend.fragment(&list)?;
+        Html::from_context(ctx, list)
     }
+    /// Renders into an existing element and returns the hooks, event
+    /// handlers and bindings stay attached, see [`Html::into_hooks`]
+    #[cfg(feature = "dom")]
     fn render_tree_into(self, parent: &mut Element)->ElementResult<BTreeMap<String, Element>>{
-        let mut map = BTreeMap::new();
-        self.render_node(parent, &mut map)?;
-        Ok(map)
+        Ok(self.render_html_into(parent)?.into_hooks())
+    }
+    /// Renders into an existing element, the appended nodes become the roots
+    #[cfg(feature = "dom")]
+    fn render_html_into(self, parent: &mut Element)->ElementResult<Html>{
+        let mut ctx = Context::new(WebBackend::new()?);
+        let existing = ctx.backend.child_nodes(parent).len();
+        self.render_node(parent, &mut ctx)?;
+        let list = ctx.backend.child_nodes(parent).split_off(existing);
+        let html = Html::from_context(ctx, list)?;
+        html.set_mounted(parent);
+        Ok(html)
     }
     
-    fn render_node(self, _parent:&mut Element, _map:&mut BTreeMap<String, Element>)->ElementResult<()>{
+    #[cfg(feature = "dom")]
+    fn render_node<B:DomBackend>(self, _parent:&mut B::Element, _ctx:&mut Context<B>)->ElementResult<()>{
         Ok(())
     }
 
@@ -54,12 +85,11 @@ impl Render for () {
 
 impl Render for &str {
     fn render<W:Write>(&self, w:&mut W)->Result{
-        write!(w, "{}", self)
+        write!(w, "{}", escape_html(*self))
     }
-    fn render_node(self, parent:&mut Element, _map:&mut BTreeMap<String, Element>)->ElementResult<()>{
-        let el = document().create_text_node(self);
-        parent.append_child(&el)?;
-        Ok(())
+    #[cfg(feature = "dom")]
+    fn render_node<B:DomBackend>(self, parent:&mut B::Element, ctx:&mut Context<B>)->ElementResult<()>{
+        ctx.backend.append_text(parent, self)
     }
 }
 
@@ -74,10 +104,11 @@ macro_rules! impl_tuple {
                 $($ident.render(w)?;)+
                 Ok(())
             }
+            #[cfg(feature = "dom")]
             #[allow(non_snake_case)]
-            fn render_node(self, parent:&mut Element, map:&mut BTreeMap<String, Element>)->ElementResult<()>{
+            fn render_node<Dom:DomBackend>(self, parent:&mut Dom::Element, ctx:&mut Context<Dom>)->ElementResult<()>{
                 let ($($ident,)+) = self;
-                $($ident.render_node(parent, map)?;)+
+                $($ident.render_node(parent, ctx)?;)+
                 Ok(())
             }
         }
@@ -92,17 +123,43 @@ macro_rules! impl_types {
                 fn render<W:Write>(&self, w:&mut W)->Result{
                     write!(w, "{}", self)
                 }
-                fn render_node(self, parent:&mut Element, _map:&mut BTreeMap<String, Element>)->ElementResult<()>{
-                    let el = document().create_text_node(&format!("{}", self));
-                    parent.append_child(&el)?;
-                    Ok(())
+                #[cfg(feature = "dom")]
+                fn render_node<B:DomBackend>(self, parent:&mut B::Element, ctx:&mut Context<B>)->ElementResult<()>{
+                    ctx.backend.append_text(parent, &self.to_string())
                 }
             }
         )+
     }
 }
 
-impl_types!{f32 f64 u128 u64 u32 u16 u8 i8 i16 i32 i64 i128 bool String usize}
+impl_types!{f32 f64 u128 u64 u32 u16 u8 i8 i16 i32 i64 i128 bool usize}
+
+impl Render for String {
+    fn render<W:Write>(&self, w:&mut W)->Result{
+        write!(w, "{}", escape_html(self))
+    }
+    #[cfg(feature = "dom")]
+    fn render_node<B:DomBackend>(self, parent:&mut B::Element, ctx:&mut Context<B>)->ElementResult<()>{
+        ctx.backend.append_text(parent, &self)
+    }
+}
+
+/// Markup that is written to the output as-is, bypassing escaping.
+///
+/// Only use it for trusted content, e.g. html produced by another
+/// renderer: `tree!{<div>{PreEscaped("<b>bold</b>")}</div>}`
+#[derive(Debug, Clone, Default)]
+pub struct PreEscaped<T:AsRef<str>>(pub T);
+
+impl<T:AsRef<str>> Render for PreEscaped<T> {
+    fn render<W:Write>(&self, w:&mut W)->Result{
+        w.write_str(self.0.as_ref())
+    }
+    #[cfg(feature = "dom")]
+    fn render_node<B:DomBackend>(self, parent:&mut B::Element, ctx:&mut Context<B>)->ElementResult<()>{
+        ctx.backend.append_html(parent, self.0.as_ref())
+    }
+}
 
 impl_tuple!{A B}
 impl_tuple!{A B C}
@@ -114,3 +171,196 @@ impl_tuple!{A B C D F G H I}
 impl_tuple!{A B C D F G H I J}
 impl_tuple!{A B C D F G H I J K}
 
+
+impl Render for &String {
+    fn render<W:Write>(&self, w:&mut W)->Result{
+        self.as_str().render(w)
+    }
+    #[cfg(feature = "dom")]
+    fn render_node<B:DomBackend>(self, parent:&mut B::Element, ctx:&mut Context<B>)->ElementResult<()>{
+        self.as_str().render_node(parent, ctx)
+    }
+}
+
+impl Render for Cow<'_, str> {
+    fn render<W:Write>(&self, w:&mut W)->Result{
+        self.as_ref().render(w)
+    }
+    #[cfg(feature = "dom")]
+    fn render_node<B:DomBackend>(self, parent:&mut B::Element, ctx:&mut Context<B>)->ElementResult<()>{
+        self.as_ref().render_node(parent, ctx)
+    }
+}
+
+impl Render for char {
+    fn render<W:Write>(&self, w:&mut W)->Result{
+        let mut buf = [0; 4];
+        (&*self.encode_utf8(&mut buf)).render(w)
+    }
+    #[cfg(feature = "dom")]
+    fn render_node<B:DomBackend>(self, parent:&mut B::Element, ctx:&mut Context<B>)->ElementResult<()>{
+        let mut buf = [0; 4];
+        (&*self.encode_utf8(&mut buf)).render_node(parent, ctx)
+    }
+}
+
+impl<T:Render> Render for Option<T> {
+    fn render<W:Write>(&self, w:&mut W)->Result{
+        if let Some(value) = self{
+            value.render(w)?;
+        }
+        Ok(())
+    }
+    #[cfg(feature = "dom")]
+    fn render_node<B:DomBackend>(self, parent:&mut B::Element, ctx:&mut Context<B>)->ElementResult<()>{
+        if let Some(value) = self{
+            value.render_node(parent, ctx)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T:Render> Render for Vec<T> {
+    fn render<W:Write>(&self, w:&mut W)->Result{
+        for item in self{
+            item.render(w)?;
+        }
+        Ok(())
+    }
+    #[cfg(feature = "dom")]
+    fn render_node<B:DomBackend>(self, parent:&mut B::Element, ctx:&mut Context<B>)->ElementResult<()>{
+        for item in self{
+            item.render_node(parent, ctx)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T:Render, const N:usize> Render for [T; N] {
+    fn render<W:Write>(&self, w:&mut W)->Result{
+        for item in self{
+            item.render(w)?;
+        }
+        Ok(())
+    }
+    #[cfg(feature = "dom")]
+    fn render_node<B:DomBackend>(self, parent:&mut B::Element, ctx:&mut Context<B>)->ElementResult<()>{
+        for item in self{
+            item.render_node(parent, ctx)?;
+        }
+        Ok(())
+    }
+}
+
+/// Borrowed items are cloned when rendered into the DOM
+impl<T:Render+Clone> Render for &[T] {
+    fn render<W:Write>(&self, w:&mut W)->Result{
+        for item in self.iter(){
+            item.render(w)?;
+        }
+        Ok(())
+    }
+    #[cfg(feature = "dom")]
+    fn render_node<B:DomBackend>(self, parent:&mut B::Element, ctx:&mut Context<B>)->ElementResult<()>{
+        for item in self{
+            item.clone().render_node(parent, ctx)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T:Render> Render for Box<T> {
+    fn render<W:Write>(&self, w:&mut W)->Result{
+        self.as_ref().render(w)
+    }
+    #[cfg(feature = "dom")]
+    fn render_node<B:DomBackend>(self, parent:&mut B::Element, ctx:&mut Context<B>)->ElementResult<()>{
+        (*self).render_node(parent, ctx)
+    }
+}
+
+/// Shared values are cloned for DOM rendering unless this is the last reference
+impl<T:Render+Clone> Render for Rc<T> {
+    fn render<W:Write>(&self, w:&mut W)->Result{
+        self.as_ref().render(w)
+    }
+    #[cfg(feature = "dom")]
+    fn render_node<B:DomBackend>(self, parent:&mut B::Element, ctx:&mut Context<B>)->ElementResult<()>{
+        Rc::try_unwrap(self)
+            .unwrap_or_else(|rc| (*rc).clone())
+            .render_node(parent, ctx)
+    }
+}
+
+/// Shared values are cloned for DOM rendering unless this is the last reference
+impl<T:Render+Clone> Render for Arc<T> {
+    fn render<W:Write>(&self, w:&mut W)->Result{
+        self.as_ref().render(w)
+    }
+    #[cfg(feature = "dom")]
+    fn render_node<B:DomBackend>(self, parent:&mut B::Element, ctx:&mut Context<B>)->ElementResult<()>{
+        Arc::try_unwrap(self)
+            .unwrap_or_else(|arc| (*arc).clone())
+            .render_node(parent, ctx)
+    }
+}
+
+/// Renders a borrowed value, `#[component]` hands it to the component
+/// function when rendering to a string. Only [`Render::render`] is forwarded.
+#[derive(Debug)]
+pub struct Borrowed<'a, T:Render>(pub &'a T);
+
+impl<T:Render> Render for Borrowed<'_, T> {
+    fn render<W:Write>(&self, w:&mut W)->Result{
+        self.0.render(w)
+    }
+}
+
+/// Renders every item of an iterator, e.g.
+/// `tree!{<ul>{RenderIter(items.iter().map(|i| tree!{<li>{*i}</li>}))}</ul>}`
+///
+/// String rendering walks a clone of the iterator, so it can be rendered repeatedly.
+#[derive(Debug, Clone)]
+pub struct RenderIter<I>(pub I)
+where I:Iterator+Clone, I::Item:Render;
+
+impl<I> Render for RenderIter<I>
+where I:Iterator+Clone, I::Item:Render
+{
+    fn render<W:Write>(&self, w:&mut W)->Result{
+        for item in self.0.clone(){
+            item.render(w)?;
+        }
+        Ok(())
+    }
+    #[cfg(feature = "dom")]
+    fn render_node<B:DomBackend>(self, parent:&mut B::Element, ctx:&mut Context<B>)->ElementResult<()>{
+        for item in self.0{
+            item.render_node(parent, ctx)?;
+        }
+        Ok(())
+    }
+}
+
+/// One of two renderable values, produced by `if` and `match` nodes in `tree!`
+#[derive(Debug, Clone)]
+pub enum Either<A, B>{
+    Left(A),
+    Right(B)
+}
+
+impl<A:Render, B:Render> Render for Either<A, B> {
+    fn render<W:Write>(&self, w:&mut W)->Result{
+        match self{
+            Either::Left(value)=>value.render(w),
+            Either::Right(value)=>value.render(w)
+        }
+    }
+    #[cfg(feature = "dom")]
+    fn render_node<Dom:DomBackend>(self, parent:&mut Dom::Element, ctx:&mut Context<Dom>)->ElementResult<()>{
+        match self{
+            Either::Left(value)=>value.render_node(parent, ctx),
+            Either::Right(value)=>value.render_node(parent, ctx)
+        }
+    }
+}
@@ -0,0 +1,305 @@
+//! Fine-grained reactive values.
+//!
+//! A [`Signal`] holds a value and notifies its subscribers whenever it is
+//! changed. [`effect`] re-runs a closure when any signal it read changes and
+//! [`computed`] derives a signal from other signals the same way.
+//!
+//! Signals can be used directly as text children or attribute values in
+//! `tree!`/`html!`. The created node is bound to the signal and updated in
+//! place when it changes, the binding lasts as long as the [`Html`](crate::Html)
+//! that owns the view:
+//! ```ignore
+//! let count = Signal::new(0);
+//! let html = html!{<span class={count.clone()}>{count.clone()}</span>}?;
+//! count.set(1); // only the text node and the `class` attribute are touched
+//! ```
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::rc::{Rc, Weak};
+use crate::render::{Render, Result, Write};
+use crate::escape::escape_html;
+#[cfg(feature = "dom")]
+use crate::utils::ElementResult;
+#[cfg(feature = "dom")]
+use crate::dom::{DomBackend, Context, Binding};
+
+thread_local!{
+    /// Effects currently running, innermost last
+    static OBSERVERS:RefCell<Vec<Rc<EffectInner>>> = const { RefCell::new(Vec::new()) };
+}
+
+enum Subscriber<T>{
+    /// Called with the new value, which stays borrowed during the call
+    Value(Rc<dyn Fn(&T)>),
+    /// Only told about the change, so it may set the signal itself (effects)
+    Change(Rc<dyn Fn()>)
+}
+
+impl<T> Clone for Subscriber<T>{
+    fn clone(&self)->Self{
+        match self{
+            Subscriber::Value(f)=>Subscriber::Value(f.clone()),
+            Subscriber::Change(f)=>Subscriber::Change(f.clone())
+        }
+    }
+}
+
+struct SignalInner<T>{
+    value:RefCell<T>,
+    subscribers:RefCell<Vec<(u64, Subscriber<T>)>>,
+    next_id:Cell<u64>,
+    /// Effect computing the value of a [`computed`] signal
+    source:RefCell<Option<Effect>>
+}
+
+/// Shared reactive value, clones refer to the same value
+pub struct Signal<T>(Rc<SignalInner<T>>);
+
+impl<T> Clone for Signal<T>{
+    fn clone(&self)->Self{
+        Signal(self.0.clone())
+    }
+}
+
+impl<T:fmt::Debug> fmt::Debug for Signal<T>{
+    fn fmt(&self, f:&mut fmt::Formatter<'_>)->fmt::Result{
+        f.debug_tuple("Signal").field(&*self.0.value.borrow()).finish()
+    }
+}
+
+impl<T:Default+'static> Default for Signal<T>{
+    fn default()->Self{
+        Signal::new(T::default())
+    }
+}
+
+impl<T:'static> Signal<T>{
+    pub fn new(value:T)->Self{
+        Signal(Rc::new(SignalInner{
+            value:RefCell::new(value),
+            subscribers:RefCell::new(vec![]),
+            next_id:Cell::new(0),
+            source:RefCell::new(None)
+        }))
+    }
+
+    /// Current value, registers the signal as a dependency of the running effect
+    pub fn get(&self)->T where T:Clone{
+        self.with(T::clone)
+    }
+
+    /// Calls `f` with the current value, tracked like [`Signal::get`]
+    pub fn with<R>(&self, f:impl FnOnce(&T)->R)->R{
+        self.track();
+        f(&self.0.value.borrow())
+    }
+
+    /// Calls `f` with the current value without registering a dependency
+    pub fn with_untracked<R>(&self, f:impl FnOnce(&T)->R)->R{
+        f(&self.0.value.borrow())
+    }
+
+    pub fn set(&self, value:T){
+        *self.0.value.borrow_mut() = value;
+        self.notify();
+    }
+
+    /// Modifies the value in place and notifies the subscribers
+    pub fn update(&self, f:impl FnOnce(&mut T)){
+        f(&mut self.0.value.borrow_mut());
+        self.notify();
+    }
+
+    /// Calls `f` on every change until the returned [`Subscription`] is dropped.
+    ///
+    /// Subscribers may read the signal but must not set it, use an [`effect`]
+    /// to write back to it.
+    pub fn subscribe<F:Fn(&T)+'static>(&self, f:F)->Subscription{
+        self.add_subscriber(Subscriber::Value(Rc::new(f)))
+    }
+
+    fn add_subscriber(&self, subscriber:Subscriber<T>)->Subscription{
+        let id = self.0.next_id.get();
+        self.0.next_id.set(id + 1);
+        self.0.subscribers.borrow_mut().push((id, subscriber));
+        let inner = Rc::downgrade(&self.0);
+        Subscription::new(move ||{
+            if let Some(inner) = inner.upgrade(){
+                inner.subscribers.borrow_mut().retain(|(sid, _)| *sid != id);
+            }
+        })
+    }
+
+    fn notify(&self){
+        // subscribers may (un)subscribe while being called
+        let subscribers:Vec<Subscriber<T>> = self.0.subscribers.borrow()
+            .iter()
+            .map(|(_, f)| f.clone())
+            .collect();
+        for subscriber in subscribers{
+            match subscriber{
+                Subscriber::Value(f)=>f(&self.0.value.borrow()),
+                Subscriber::Change(f)=>f()
+            }
+        }
+    }
+
+    fn track(&self){
+        let observer = OBSERVERS.with(|observers| observers.borrow().last().cloned());
+        if let Some(observer) = observer{
+            observer.track(self);
+        }
+    }
+}
+
+/// Removes a subscriber from its signal when dropped
+#[must_use = "the subscription ends when it is dropped"]
+pub struct Subscription{
+    unsubscribe:Option<Box<dyn FnOnce()>>
+}
+
+impl Subscription{
+    fn new<F:FnOnce()+'static>(unsubscribe:F)->Self{
+        Self{
+            unsubscribe:Some(Box::new(unsubscribe))
+        }
+    }
+}
+
+impl fmt::Debug for Subscription{
+    fn fmt(&self, f:&mut fmt::Formatter<'_>)->fmt::Result{
+        f.write_str("Subscription")
+    }
+}
+
+impl Drop for Subscription{
+    fn drop(&mut self){
+        if let Some(unsubscribe) = self.unsubscribe.take(){
+            unsubscribe();
+        }
+    }
+}
+
+struct EffectInner{
+    run:RefCell<Box<dyn FnMut()>>,
+    /// Signals read by the last run, keyed by their address
+    sources:RefCell<Vec<(*const (), Subscription)>>
+}
+
+impl EffectInner{
+    fn track<T:'static>(self:&Rc<Self>, signal:&Signal<T>){
+        let address = Rc::as_ptr(&signal.0) as *const ();
+        if self.sources.borrow().iter().any(|(source, _)| *source == address){
+            return;
+        }
+        let effect = Rc::downgrade(self);
+        let subscription = signal.add_subscriber(Subscriber::Change(Rc::new(move ||{
+            if let Some(effect) = effect.upgrade(){
+                EffectInner::run(&effect);
+            }
+        })));
+        self.sources.borrow_mut().push((address, subscription));
+    }
+
+    fn run(self:&Rc<Self>){
+        // a change made by the effect itself does not re-run it
+        let mut run = match self.run.try_borrow_mut(){
+            Ok(run)=>run,
+            Err(_)=>return
+        };
+        let sources = std::mem::take(&mut *self.sources.borrow_mut());
+        drop(sources);
+        OBSERVERS.with(|observers| observers.borrow_mut().push(self.clone()));
+        run();
+        OBSERVERS.with(|observers| observers.borrow_mut().pop());
+    }
+}
+
+/// Handle of a running [`effect`], the effect stops when it is dropped
+#[must_use = "the effect stops when it is dropped"]
+pub struct Effect(Rc<EffectInner>);
+
+impl fmt::Debug for Effect{
+    fn fmt(&self, f:&mut fmt::Formatter<'_>)->fmt::Result{
+        f.debug_struct("Effect").field("sources", &self.0.sources.borrow().len()).finish()
+    }
+}
+
+/// Runs `f` now and again whenever a signal it read changes
+pub fn effect<F:FnMut()+'static>(f:F)->Effect{
+    let inner = Rc::new(EffectInner{
+        run:RefCell::new(Box::new(f)),
+        sources:RefCell::new(vec![])
+    });
+    EffectInner::run(&inner);
+    Effect(inner)
+}
+
+/// Signal holding the result of `f`, recomputed when a signal read by `f` changes
+pub fn computed<T:'static, F:Fn()->T+'static>(f:F)->Signal<T>{
+    let target:Rc<RefCell<Weak<SignalInner<T>>>> = Rc::new(RefCell::new(Weak::new()));
+    let initial = Rc::new(RefCell::new(None));
+    let source = effect({
+        let target = target.clone();
+        let initial = initial.clone();
+        move ||{
+            let value = f();
+            let signal = target.borrow().upgrade();
+            match signal{
+                Some(inner)=>Signal(inner).set(value),
+                None=>*initial.borrow_mut() = Some(value)
+            }
+        }
+    });
+    let value = initial.borrow_mut().take().unwrap();
+    let signal = Signal::new(value);
+    *target.borrow_mut() = Rc::downgrade(&signal.0);
+    *signal.0.source.borrow_mut() = Some(source);
+    signal
+}
+
+/// Value the DOM can be bound to, seen as text
+pub trait Reactive{
+    /// Current value, without registering a dependency
+    fn current(&self)->String;
+    fn subscribe_text(&self, f:Box<dyn Fn(String)>)->Subscription;
+}
+
+impl<T:fmt::Display+'static> Reactive for Signal<T>{
+    fn current(&self)->String{
+        self.with_untracked(T::to_string)
+    }
+    fn subscribe_text(&self, f:Box<dyn Fn(String)>)->Subscription{
+        self.subscribe(move |value| f(value.to_string()))
+    }
+}
+
+impl fmt::Debug for dyn Reactive{
+    fn fmt(&self, f:&mut fmt::Formatter<'_>)->fmt::Result{
+        f.debug_tuple("Reactive").field(&self.current()).finish()
+    }
+}
+
+/// Rendered as a text node that follows the signal
+impl<T:fmt::Display+'static> Render for Signal<T>{
+    fn render<W:Write>(&self, w:&mut W)->Result{
+        write!(w, "{}", escape_html(self.current()))
+    }
+    #[cfg(feature = "dom")]
+    fn render_node<B:DomBackend>(self, parent:&mut B::Element, ctx:&mut Context<B>)->ElementResult<()>{
+        let text = ctx.backend.create_text(&self.current())?;
+        ctx.backend.append_child(parent, &text)?;
+        ctx.bind(Binding::Text(text), Box::new(self));
+        Ok(())
+    }
+}
+
+impl<T:fmt::Display+'static> Render for &Signal<T>{
+    fn render<W:Write>(&self, w:&mut W)->Result{
+        (*self).render(w)
+    }
+    #[cfg(feature = "dom")]
+    fn render_node<B:DomBackend>(self, parent:&mut B::Element, ctx:&mut Context<B>)->ElementResult<()>{
+        self.clone().render_node(parent, ctx)
+    }
+}
@@ -4,19 +4,17 @@ pub use web_sys::{
     Element
 };
 pub use wasm_bindgen::prelude::JsValue;
+pub use crate::error::{Error, ElementResult};
 
-pub type ElementResult<T> = std::result::Result<T, JsValue>;
-
-pub fn document() -> Document {
-    let window = web_sys::window().expect("no global `window` exists");
-    let document = window.document().expect("unable to get `document` node");
-    document
+pub fn window() -> ElementResult<Window> {
+    web_sys::window().ok_or(Error::NoWindow)
 }
 
-pub fn window() -> Window {
-    web_sys::window().expect("no global `window` exists")
+pub fn document() -> ElementResult<Document> {
+    window()?.document().ok_or(Error::NoDocument)
 }
 
+/// `None` also when there is no document
 pub fn get_element_by_id(id : &str) -> Option<Element> {
-    document().get_element_by_id(id)
+    document().ok()?.get_element_by_id(id)
 }
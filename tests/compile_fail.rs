@@ -0,0 +1,13 @@
+//! Diagnostics of the macros, refresh the `.stderr` files with
+//! `TRYBUILD=overwrite cargo test --test compile_fail`, `tests/pass` holds
+//! macro input that has to keep compiling
+//!
+//! The hints rustc adds depend on the enabled features, the expected output
+//! is recorded with the default ones.
+#[test]
+#[cfg(feature = "dom")]
+fn compile_fail(){
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+    t.pass("tests/pass/*.rs");
+}
@@ -0,0 +1,20 @@
+use flow_html::{tree, component, Render};
+
+#[component]
+fn Card(title:String)->impl Render{
+    tree!{<section><h2>{title}</h2></section>}
+}
+
+fn main(){
+    let ids = vec![1, 2];
+    let view = tree!{
+        <div @cards>
+            for id in ids { <Card key={id} title={format!("card {}", id)} /> }
+            <Card @last title={"last".to_string()} />
+        </div>
+    };
+    assert_eq!(
+        view.html(),
+        "<div><section><h2>card 1</h2></section><section><h2>card 2</h2></section><section><h2>last</h2></section></div>"
+    );
+}
@@ -0,0 +1,10 @@
+use flow_html::renderable;
+
+#[renderable(flow-chip)]
+struct FlowChip<R:flow_html::Render>{
+    #[attr(default)]
+    pub label:String,
+    pub children:Option<R>
+}
+
+fn main(){}
@@ -0,0 +1,14 @@
+use flow_html::{component, tree, Render};
+
+struct Config{
+    title:&'static str
+}
+
+#[component]
+fn Panel(config:Config)->impl Render{
+    tree!{<p>{config.title}</p>}
+}
+
+fn main(){
+    let _ = tree!{<Panel config={Config{title:"a"}} />}.html();
+}
@@ -0,0 +1,14 @@
+use flow_html::{component, tree, Render};
+
+#[component]
+fn Card(title:&str, children:impl Render)->impl Render{
+    tree!{<section><h2>{title}</h2>{children}</section>}
+}
+
+fn main(){
+    let _ = tree!{
+        <div>
+            <Card>"body"</Card>
+        </div>
+    };
+}
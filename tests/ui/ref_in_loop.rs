@@ -0,0 +1,10 @@
+use flow_html::tree;
+
+fn main(){
+    let rows = vec!["a", "b"];
+    let _ = tree!{
+        <ul>
+            for row in rows { <li @row>{row}</li> }
+        </ul>
+    };
+}
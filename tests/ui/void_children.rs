@@ -0,0 +1,9 @@
+use flow_html::tree;
+
+fn main(){
+    let _ = tree!{
+        <p>
+            <br>"line"</br>
+        </p>
+    };
+}